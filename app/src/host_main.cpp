@@ -92,6 +92,8 @@ int main(int argc, const char* argv[]) {
               "Now if I wanted to print a really long text I can use %%k: %k",
               interned_string);
 
+    LOG_INFO(&logger, "Temperature: %6.2f C, sensor id: %#06x", 23.5f, 0x2Au);
+
     iteration++;
   }
 }
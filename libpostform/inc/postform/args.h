@@ -13,6 +13,7 @@ class Argument {
   const union {
     unsigned long long unsigned_long_long;
     signed long long signed_long_long;
+    double double_value;
     const char* str_ptr;
     const void* void_ptr;
     InternedString interned_string;
@@ -23,7 +24,8 @@ class Argument {
     SIGNED_INTEGER,
     STRING_POINTER,
     VOID_PTR,
-    INTERNED_STRING
+    INTERNED_STRING,
+    DOUBLE
   } type;
 };
 
@@ -45,6 +47,13 @@ constexpr Argument make_arg(T value) {
   };
 }
 
+template <class T,
+          std::enable_if_t<std::is_floating_point_v<T>, bool> = true>
+constexpr Argument make_arg(T value) {
+  return Argument{.double_value = static_cast<double>(value),
+                  .type = Argument::Type::DOUBLE};
+}
+
 template <class T,
           std::enable_if_t<std::is_convertible_v<T, const char*>, bool> = true>
 constexpr Argument make_arg(T value) {
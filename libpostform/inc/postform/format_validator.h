@@ -2,6 +2,7 @@
 #define POSTFORM_FORMAT_VALIDATOR_H_
 
 #include <array>
+#include <cstddef>
 #include <cstdint>
 #include <type_traits>
 
@@ -19,7 +20,7 @@ struct SizeSpecHandler {
 constexpr static SizeSpecHandler default_size_handler{
     "", [](std::size_t size [[maybe_unused]]) { return true; }};
 
-constexpr static std::array<SizeSpecHandler, 5> integer_size_handlers = {
+constexpr static std::array<SizeSpecHandler, 8> integer_size_handlers = {
     SizeSpecHandler{"", [](std::size_t size) { return size == sizeof(int); }},
     SizeSpecHandler{"l",
                     [](std::size_t size) { return size == sizeof(long int); }},
@@ -29,6 +30,23 @@ constexpr static std::array<SizeSpecHandler, 5> integer_size_handlers = {
                     [](std::size_t size) { return size == sizeof(char); }},
     SizeSpecHandler{"h",
                     [](std::size_t size) { return size == sizeof(short); }},
+    SizeSpecHandler{
+        "z", [](std::size_t size) { return size == sizeof(std::size_t); }},
+    SizeSpecHandler{
+        "j", [](std::size_t size) { return size == sizeof(std::intmax_t); }},
+    SizeSpecHandler{
+        "t", [](std::size_t size) { return size == sizeof(std::ptrdiff_t); }},
+};
+
+// Float arguments are promoted to double, so both float and double are
+// accepted without a size specifier.
+constexpr static std::array<SizeSpecHandler, 3> float_size_handlers = {
+    SizeSpecHandler{"",
+                    [](std::size_t size) { return size <= sizeof(double); }},
+    SizeSpecHandler{"l",
+                    [](std::size_t size) { return size <= sizeof(double); }},
+    SizeSpecHandler{
+        "L", [](std::size_t size) { return size == sizeof(long double); }},
 };
 
 struct SizeSpecHandlers {
@@ -36,6 +54,31 @@ struct SizeSpecHandlers {
   uint32_t num = 1;
 };
 
+/**
+ * @brief Returns the number of characters taken by the flags, field width and
+ * precision of a conversion specification.
+ *
+ * @param fmt points to the first character after the '%' sign.
+ */
+[[nodiscard]] constexpr static std::size_t conversionPrefixLength(
+    const char* fmt) {
+  std::size_t i = 0;
+  while (fmt[i] == '-' || fmt[i] == '+' || fmt[i] == ' ' || fmt[i] == '#' ||
+         fmt[i] == '0') {
+    i++;
+  }
+  while (fmt[i] >= '0' && fmt[i] <= '9') {
+    i++;
+  }
+  if (fmt[i] == '.') {
+    i++;
+    while (fmt[i] >= '0' && fmt[i] <= '9') {
+      i++;
+    }
+  }
+  return i;
+}
+
 struct FormatSpecHandler {
   const SizeSpecHandlers size_handlers;
   const char* format_spec;
@@ -47,7 +90,7 @@ template <class T>
     const char* fmt, [[maybe_unused]] T arg, std::size_t* position) {
   // This array needs to be defined inside the template in order to have
   // visibility of T.
-  constexpr std::array<FormatSpecHandler, 9> format_spec_handlers = {
+  constexpr std::array<FormatSpecHandler, 18> format_spec_handlers = {
       FormatSpecHandler{SizeSpecHandlers{}, "s",
                         []() { return std::is_convertible_v<T, const char*>; }},
       FormatSpecHandler{
@@ -68,6 +111,33 @@ template <class T>
       FormatSpecHandler{SizeSpecHandlers{integer_size_handlers.data(),
                                          integer_size_handlers.size()},
                         "x", []() { return std::is_integral_v<T>; }},
+      FormatSpecHandler{SizeSpecHandlers{integer_size_handlers.data(),
+                                         integer_size_handlers.size()},
+                        "X", []() { return std::is_integral_v<T>; }},
+      FormatSpecHandler{SizeSpecHandlers{float_size_handlers.data(),
+                                         float_size_handlers.size()},
+                        "f", []() { return std::is_floating_point_v<T>; }},
+      FormatSpecHandler{SizeSpecHandlers{float_size_handlers.data(),
+                                         float_size_handlers.size()},
+                        "F", []() { return std::is_floating_point_v<T>; }},
+      FormatSpecHandler{SizeSpecHandlers{float_size_handlers.data(),
+                                         float_size_handlers.size()},
+                        "e", []() { return std::is_floating_point_v<T>; }},
+      FormatSpecHandler{SizeSpecHandlers{float_size_handlers.data(),
+                                         float_size_handlers.size()},
+                        "E", []() { return std::is_floating_point_v<T>; }},
+      FormatSpecHandler{SizeSpecHandlers{float_size_handlers.data(),
+                                         float_size_handlers.size()},
+                        "g", []() { return std::is_floating_point_v<T>; }},
+      FormatSpecHandler{SizeSpecHandlers{float_size_handlers.data(),
+                                         float_size_handlers.size()},
+                        "G", []() { return std::is_floating_point_v<T>; }},
+      FormatSpecHandler{SizeSpecHandlers{float_size_handlers.data(),
+                                         float_size_handlers.size()},
+                        "a", []() { return std::is_floating_point_v<T>; }},
+      FormatSpecHandler{SizeSpecHandlers{float_size_handlers.data(),
+                                         float_size_handlers.size()},
+                        "A", []() { return std::is_floating_point_v<T>; }},
       FormatSpecHandler{SizeSpecHandlers{}, "p",
                         []() { return std::is_pointer_v<T>; }},
       FormatSpecHandler{
@@ -85,6 +155,7 @@ template <class T>
   std::size_t i = 0;
   while (fmt[i] != '\0') {
     if (fmt[i++] == '%') {
+      i += conversionPrefixLength(&fmt[i]);
       if (fmt[i] == '%') {
         i++;
         continue;
@@ -123,8 +194,12 @@ template <class T>
 [[nodiscard]] constexpr static bool formatValidator(const char* fmt) {
   std::size_t i = 0;
   while (fmt[i] != '\0') {
-    if (fmt[i] == '%' && fmt[++i] != '%') {
-      return false;
+    if (fmt[i] == '%') {
+      i++;
+      i += conversionPrefixLength(&fmt[i]);
+      if (fmt[i] != '%') {
+        return false;
+      }
     }
     i++;
   }
@@ -134,10 +134,31 @@ class Logger {
           writeLeb128(&writer, ptr);
           break;
         }
+        case Argument::Type::DOUBLE: {
+          writeDouble(&writer, argument.double_value);
+          break;
+        }
       }
     }
   }
 
+  /**
+   * @brief Writes a double as its 8-byte IEEE-754 representation in
+   *        little-endian byte order.
+   */
+  void writeDouble(Writer* writer, double value) {
+    static_assert(sizeof(double) == sizeof(uint64_t),
+                  "Postform requires 64-bit doubles");
+    uint64_t bits;
+    memcpy(&bits, &value, sizeof(bits));
+
+    uint8_t buffer[sizeof(bits)];
+    for (std::size_t i = 0; i < sizeof(bits); i++) {
+      buffer[i] = static_cast<uint8_t>(bits >> (8 * i));
+    }
+    writer->write(buffer, sizeof(buffer));
+  }
+
   template <class T,
             std::enable_if_t<std::is_integral_v<T> && std::is_unsigned_v<T>,
                              bool> = true>
@@ -18,6 +18,25 @@ static_assert(POSTFORM_VALIDATE_FORMAT("%x", 12));
 
 static_assert(POSTFORM_VALIDATE_FORMAT("%d", -123));
 
+// Flags, field width and precision are accepted before the conversion
+static_assert(POSTFORM_VALIDATE_FORMAT("%08x", 12u));
+static_assert(POSTFORM_VALIDATE_FORMAT("%-12s", ""));
+static_assert(POSTFORM_VALIDATE_FORMAT("%.3s", ""));
+static_assert(POSTFORM_VALIDATE_FORMAT("%+5.2d", 1));
+static_assert(POSTFORM_VALIDATE_FORMAT("%#X", 12u));
+static_assert(POSTFORM_VALIDATE_FORMAT("%zu", sizeof(int)));
+static_assert(POSTFORM_VALIDATE_FORMAT("%5%"));
+static_assert(!POSTFORM_VALIDATE_FORMAT("%08s", 12u));
+
+// Floating point conversions
+static_assert(POSTFORM_VALIDATE_FORMAT("%f %e %g %a", 1.0f, 1.0, 1.0, 1.0));
+static_assert(POSTFORM_VALIDATE_FORMAT("%.2F %10.3E %-8G %.1A", 1.0, 1.0, 1.0,
+                                       1.0));
+static_assert(POSTFORM_VALIDATE_FORMAT("%lf", 1.0));
+static_assert(POSTFORM_VALIDATE_FORMAT("%Lf", 1.0L));
+static_assert(!POSTFORM_VALIDATE_FORMAT("%f", 1));
+static_assert(!POSTFORM_VALIDATE_FORMAT("%d", 1.0));
+
 // Compile-time tests for the POSTFORM_ASSERT_FORMAT
 POSTFORM_ASSERT_FORMAT("%u %u", 2u, 1u);
 POSTFORM_ASSERT_FORMAT("%s", "random_str");
@@ -28,6 +28,8 @@ class LoggerTest : public TestWithParam<Leb128Params> {
     logger.writeLeb128(&writer, value);
   }
 
+  void writeDouble(double value) { logger.writeDouble(&writer, value); }
+
   MockLogger logger;
   StrictMock<MockWriter> writer;
 };
@@ -68,4 +70,11 @@ INSTANTIATE_TEST_SUITE_P(
            Leb128Params{std::variant<int64_t, uint64_t>(int64_t{-255}),
                         std::vector<uint8_t>{0x81, 0x7E}}));
 
+TEST_F(LoggerTest, DoubleIsLittleEndianIeee754) {
+  EXPECT_CALL(writer, write(_, _))
+      .With(ElementsAreArray(
+          std::vector<uint8_t>{0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x37, 0x40}));
+  writeDouble(23.5);
+}
+
 }  // namespace Postform
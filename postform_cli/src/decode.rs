@@ -0,0 +1,78 @@
+use crate::{report_error, CommonOpts};
+use color_eyre::eyre::Result;
+use postform_decoder::Decoder;
+use std::io::{self, BufRead};
+use structopt::StructOpt;
+
+/// Decodes hex encoded log messages.
+#[derive(Debug, StructOpt)]
+#[structopt(group = CommonOpts::elf_group(&[]))]
+pub struct Opts {
+    #[structopt(flatten)]
+    pub common: CommonOpts,
+
+    /// Hex encoded log messages, as sent by the target before any framing. If no message is
+    /// given, messages are read from stdin, one per line.
+    #[structopt(name = "MESSAGE")]
+    messages: Vec<String>,
+}
+
+/// Error returned for messages that are not valid hex strings.
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid hex message \"{0}\"")]
+pub struct InvalidHexMessage(String);
+
+/// Parses a hex string, ignoring any whitespace in it.
+fn parse_hex(message: &str) -> Result<Vec<u8>, InvalidHexMessage> {
+    let digits: Vec<u32> = message
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_digit(16))
+        .collect::<Option<_>>()
+        .ok_or_else(|| InvalidHexMessage(message.to_owned()))?;
+    if digits.len() % 2 != 0 {
+        return Err(InvalidHexMessage(message.to_owned()));
+    }
+    Ok(digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4 | pair[1]) as u8)
+        .collect())
+}
+
+/// Runs the decode command.
+pub fn run(opts: Opts) -> Result<()> {
+    let elf_metadata = opts.common.elf_metadata()?;
+    let mut decoder = Decoder::new(&elf_metadata);
+    let printer = opts.common.printer(false);
+    let mut decode_message = |message: &str| match parse_hex(message) {
+        Ok(message) => printer.print_log(decoder.decode(&message)),
+        Err(error) => report_error(error),
+    };
+
+    if opts.messages.is_empty() {
+        for line in io::stdin().lock().lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                decode_message(&line);
+            }
+        }
+    } else {
+        for message in &opts.messages {
+            decode_message(message);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(parse_hex("00 1a\tFf").unwrap(), [0x00, 0x1a, 0xff]);
+        assert!(parse_hex("").unwrap().is_empty());
+        assert!(parse_hex("abc").is_err());
+        assert!(parse_hex("0g").is_err());
+    }
+}
@@ -0,0 +1,35 @@
+use crate::CommonOpts;
+use color_eyre::eyre::Result;
+use std::{fs, path::PathBuf};
+use structopt::{clap::ArgGroup, StructOpt};
+
+/// Writes the log metadata of an ELF file to a standalone dictionary file.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    group = CommonOpts::elf_group(&[]),
+    group = ArgGroup::with_name("output-or-exemption")
+        .args(&["OUTPUT", "version"])
+        .multiple(true)
+        .required(true)
+)]
+pub struct Opts {
+    #[structopt(flatten)]
+    pub common: CommonOpts,
+
+    /// Path of the dictionary file to write.
+    #[structopt(name = "OUTPUT", parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+/// Runs the export-dictionary command.
+pub fn run(opts: Opts) -> Result<()> {
+    let elf_metadata = opts.common.elf_metadata()?;
+    let mut dictionary = vec![];
+    elf_metadata.write_dictionary(&mut dictionary)?;
+    // The output is only optional when only the version is requested
+    fs::write(
+        opts.output.expect("OUTPUT argument is required"),
+        dictionary,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,390 @@
+//! Command line tools for Postform.
+//!
+//! The `postform` binary exposes every transport as a subcommand. The `postform_rtt`,
+//! `postform_serial` and `postform_persist` binaries are aliases of the `rtt`, `serial` and
+//! `persist` subcommands.
+
+pub mod decode;
+pub mod export_dictionary;
+pub mod persist;
+pub mod replay;
+pub mod rtt;
+pub mod serial;
+pub mod target;
+pub mod tcp;
+
+use color_eyre::eyre::Result;
+use colored::Colorize;
+use postform_decoder::{
+    CaptureHeader, CaptureWriter, DefaultFormatter, ElfMetadata, FileGlob, JsonFormatter,
+    LineRange, Log, LogFilter, LogFormatter, LogLevel, OutputFormat, SerialDecoder,
+    TemplateFormatter, TimestampFormat, POSTFORM_VERSION,
+};
+use regex::Regex;
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::SystemTime,
+};
+use structopt::{clap::ArgGroup, StructOpt};
+
+// Options shared by all commands. Not a doc comment, as it would override the description of
+// the commands that flatten it.
+#[derive(Debug, StructOpt)]
+pub struct CommonOpts {
+    /// Path to an ELF firmware file, a metadata dictionary or a capture with an embedded
+    /// dictionary.
+    #[structopt(name = "ELF", parse(from_os_str))]
+    pub elf: Option<PathBuf>,
+
+    /// Disables FW version check.
+    #[structopt(long, short = "d")]
+    pub disable_version_check: bool,
+
+    /// Shows the version information.
+    #[structopt(long, short = "V")]
+    pub version: bool,
+
+    /// Output format of the decoded logs: "text" or "json".
+    #[structopt(long, default_value = "text")]
+    pub format: OutputFormat,
+
+    /// Timestamps printed with each log: "target", "host" or "both". Host timestamps are the
+    /// wall-clock time at which the logs were received, only available for live transports and
+    /// captures.
+    #[structopt(long, default_value = "target")]
+    pub timestamps: TimestampFormat,
+
+    /// Template of the text output, replacing the default two-line layout. For instance
+    /// "{t:>10.3} {L:<7} {file}:{line} {msg}". Available fields: t (target time), T (host time),
+    /// C (channel), core, L (level), file, line, msg and fmt (format string). Ignored with
+    /// --format json.
+    #[structopt(long)]
+    pub log_format: Option<TemplateFormatter>,
+
+    /// Disables the colors of the text output.
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// Only prints logs of this level or higher: "debug", "info", "warning" or "error". Logs of
+    /// unknown level are not printed either.
+    #[structopt(long)]
+    pub min_level: Option<LogLevel>,
+
+    /// Only prints logs from files matching this glob, e.g. "drivers/*.cpp". The glob matches the
+    /// end of the file name. Can be given multiple times.
+    #[structopt(long, number_of_values = 1)]
+    pub include_file: Vec<FileGlob>,
+
+    /// Doesn't print logs from files matching this glob. Can be given multiple times.
+    #[structopt(long, number_of_values = 1)]
+    pub exclude_file: Vec<FileGlob>,
+
+    /// Only prints logs whose message matches this regex. Can be given multiple times.
+    #[structopt(long, number_of_values = 1)]
+    pub message: Vec<Regex>,
+
+    /// Only prints logs from lines in this range, e.g. "10-20", "10-", "-20" or "15". Can be
+    /// given multiple times.
+    #[structopt(long, number_of_values = 1)]
+    pub lines: Vec<LineRange>,
+}
+
+impl CommonOpts {
+    /// Group making the ELF argument required unless --version or one of the given flags of the
+    /// command is present. Each command declares it with the flags that exit before loading the
+    /// ELF file.
+    pub fn elf_group(exemptions: &[&'static str]) -> ArgGroup<'static> {
+        ArgGroup::with_name("ELF-or-exemption")
+            .args(&["ELF", "version"])
+            .args(exemptions)
+            .multiple(true)
+            .required(true)
+    }
+
+    /// Path of the ELF file, dictionary or capture given in the command line.
+    pub fn elf_path(&self) -> &Path {
+        // The ELF argument is only optional when the command exits early
+        self.elf.as_ref().expect("ELF argument is required")
+    }
+
+    /// Loads the log metadata from the ELF file, dictionary or capture given in the command line.
+    pub fn elf_metadata(&self) -> Result<ElfMetadata> {
+        Ok(ElfMetadata::from_file(
+            self.elf_path(),
+            self.disable_version_check,
+        )?)
+    }
+
+    /// Builds the printer of the decoded logs from the output and filtering options. `show_core`
+    /// prints the core of each log, for the commands that decode the logs of several cores.
+    pub fn printer(&self, show_core: bool) -> Printer {
+        let mut filter = LogFilter::new();
+        if let Some(level) = self.min_level {
+            filter = filter.min_level(level);
+        }
+        for glob in &self.include_file {
+            filter = filter.include_file(glob.clone());
+        }
+        for glob in &self.exclude_file {
+            filter = filter.exclude_file(glob.clone());
+        }
+        for regex in &self.message {
+            filter = filter.message(regex.clone());
+        }
+        for range in &self.lines {
+            filter = filter.line_range(range.clone());
+        }
+
+        // Colors also follow NO_COLOR and are disabled when stdout is not a terminal
+        let color = !self.no_color && colored::control::SHOULD_COLORIZE.should_colorize();
+        let formatter: Box<dyn LogFormatter> = match (self.format, &self.log_format) {
+            (OutputFormat::Json, _) => Box::new(JsonFormatter),
+            (OutputFormat::Text, Some(template)) => Box::new(template.clone().color(color)),
+            (OutputFormat::Text, None) => Box::new(
+                DefaultFormatter::new(self.timestamps)
+                    .color(color)
+                    .show_core(show_core),
+            ),
+        };
+        Printer { filter, formatter }
+    }
+}
+
+/// Prints the decoded logs that pass the filter given in the command line.
+pub struct Printer {
+    filter: LogFilter,
+    formatter: Box<dyn LogFormatter>,
+}
+
+impl Printer {
+    /// Prints a decoded log to stdout if it passes the filter, or reports the error that
+    /// prevented decoding it.
+    pub fn print_log<E: Display>(&self, log: Result<Log, E>) {
+        let log = match log {
+            Ok(log) => log,
+            Err(error) => return report_error(error),
+        };
+        if !self.filter.matches(&log) {
+            return;
+        }
+
+        match self.formatter.format(&log, &mut io::stdout().lock()) {
+            Ok(()) => {}
+            // The reader of the output went away, e.g. `postform ... | head`
+            Err(error) if error.kind() == io::ErrorKind::BrokenPipe => std::process::exit(0),
+            Err(error) => report_error(format!("Error printing log: {}", error)),
+        }
+    }
+}
+
+// Options of the commands that receive rcobs framed logs from a SerialLogger.
+#[derive(Debug, StructOpt)]
+pub struct FramingOpts {
+    /// Maximum length of a received frame. Longer frames are dropped.
+    #[structopt(long, default_value = "4096")]
+    pub max_frame_length: usize,
+}
+
+impl FramingOpts {
+    /// Creates a SerialDecoder configured with these options.
+    pub fn serial_decoder<'a>(&self, elf_metadata: &'a ElfMetadata) -> SerialDecoder<'a> {
+        SerialDecoder::with_max_frame_len(elf_metadata, self.max_frame_length)
+    }
+}
+
+// Options of the commands that read from a live transport.
+#[derive(Debug, StructOpt)]
+pub struct RecordOpts {
+    /// Records the raw bytes received from the target to a capture file, along with the host
+    /// time at which they were received. The logs are still decoded as usual.
+    #[structopt(long, parse(from_os_str))]
+    pub record: Option<PathBuf>,
+
+    /// Embeds the metadata dictionary in the capture file, so that it can be decoded without the
+    /// ELF file.
+    #[structopt(long, requires("record"))]
+    pub embed_dictionary: bool,
+
+    /// Version of the firmware, stored in the header of the capture file, e.g. the output of
+    /// `git describe` when building it.
+    #[structopt(long, requires("record"))]
+    pub firmware_version: Option<String>,
+}
+
+impl RecordOpts {
+    /// Creates the capture file if recording was requested. The header of the capture describes
+    /// the firmware in `common` and the `transport` used to receive the data.
+    pub fn recorder(
+        &self,
+        common: &CommonOpts,
+        elf_metadata: &ElfMetadata,
+        transport: &str,
+    ) -> Result<Recorder> {
+        let capture = match &self.record {
+            Some(path) => {
+                let elf_data = fs::read(common.elf_path())?;
+                let header = CaptureHeader::new(
+                    elf_metadata,
+                    &elf_data,
+                    transport,
+                    self.firmware_version.as_deref(),
+                    self.embed_dictionary,
+                )?;
+                Some(CaptureWriter::new(File::create(path)?, &header)?)
+            }
+            None => None,
+        };
+        Ok(Recorder { capture })
+    }
+}
+
+/// Records the data received from a transport if requested in the command line.
+pub struct Recorder {
+    capture: Option<CaptureWriter<File>>,
+}
+
+impl Recorder {
+    /// Appends the data received at `timestamp` to the capture file. Does nothing if recording
+    /// is disabled.
+    pub fn record(&mut self, timestamp: SystemTime, data: &[u8]) -> Result<()> {
+        if let Some(capture) = &mut self.capture {
+            capture.write_chunk(timestamp, data)?;
+        }
+        Ok(())
+    }
+}
+
+// Available commands.
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    Rtt(rtt::Opts),
+    Serial(serial::Opts),
+    Tcp(tcp::Opts),
+    Persist(persist::Opts),
+    Replay(replay::Opts),
+    Decode(decode::Opts),
+    ExportDictionary(export_dictionary::Opts),
+}
+
+impl Command {
+    fn common(&self) -> &CommonOpts {
+        match self {
+            Command::Rtt(opts) => &opts.common,
+            Command::Serial(opts) => &opts.common,
+            Command::Tcp(opts) => &opts.common,
+            Command::Persist(opts) => &opts.common,
+            Command::Replay(opts) => &opts.common,
+            Command::Decode(opts) => &opts.common,
+            Command::ExportDictionary(opts) => &opts.common,
+        }
+    }
+
+    /// Runs the command until it is done or the user interrupts it. `bin_name` is the name of
+    /// the binary running it, printed by --version.
+    pub fn run(self, bin_name: &str) -> Result<()> {
+        if self.common().version {
+            print_version(bin_name);
+            return Ok(());
+        }
+
+        match self {
+            Command::Rtt(opts) => rtt::run(opts),
+            Command::Serial(opts) => serial::run(opts),
+            Command::Tcp(opts) => tcp::run(opts),
+            Command::Persist(opts) => persist::run(opts),
+            Command::Replay(opts) => replay::run(opts),
+            Command::Decode(opts) => decode::run(opts),
+            Command::ExportDictionary(opts) => export_dictionary::run(opts),
+        }
+    }
+}
+
+impl From<rtt::Opts> for Command {
+    fn from(opts: rtt::Opts) -> Self {
+        Command::Rtt(opts)
+    }
+}
+
+impl From<serial::Opts> for Command {
+    fn from(opts: serial::Opts) -> Self {
+        Command::Serial(opts)
+    }
+}
+
+impl From<tcp::Opts> for Command {
+    fn from(opts: tcp::Opts) -> Self {
+        Command::Tcp(opts)
+    }
+}
+
+impl From<persist::Opts> for Command {
+    fn from(opts: persist::Opts) -> Self {
+        Command::Persist(opts)
+    }
+}
+
+impl From<replay::Opts> for Command {
+    fn from(opts: replay::Opts) -> Self {
+        Command::Replay(opts)
+    }
+}
+
+impl From<decode::Opts> for Command {
+    fn from(opts: decode::Opts) -> Self {
+        Command::Decode(opts)
+    }
+}
+
+impl From<export_dictionary::Opts> for Command {
+    fn from(opts: export_dictionary::Opts) -> Self {
+        Command::ExportDictionary(opts)
+    }
+}
+
+/// Sets up error reporting and logging for the binaries.
+pub fn init() -> Result<()> {
+    color_eyre::install()?;
+    env_logger::init();
+    Ok(())
+}
+
+/// Entry point of the binaries that are aliases of a single command, named `name`.
+pub fn run_alias<T>(name: &str) -> Result<()>
+where
+    T: StructOpt + Into<Command>,
+{
+    init()?;
+    let opts = T::from_clap(&T::clap().name(name).get_matches());
+    opts.into().run(name)
+}
+
+/// Prints the version of the binary named `bin_name` and the supported Postform version.
+pub fn print_version(bin_name: &str) {
+    // version from Cargo.toml e.g. "0.1.4"
+    println!("{} {}", bin_name, env!("CARGO_PKG_VERSION"));
+    println!("supported Postform version: {}", POSTFORM_VERSION);
+}
+
+/// Prints an error to stderr, so that it doesn't get mixed with the decoded logs.
+pub fn report_error<E: Display>(error: E) {
+    eprintln!("{}", error.to_string().red());
+}
+
+/// Installs a ctrl-c handler. The returned flag is cleared once the user requests to exit.
+pub fn install_ctrlc_handler() -> Result<Arc<AtomicBool>> {
+    let is_app_running = Arc::new(AtomicBool::new(true));
+    {
+        let is_app_running = is_app_running.clone();
+        ctrlc::set_handler(move || {
+            is_app_running.store(false, Ordering::Relaxed);
+        })?;
+    }
+    Ok(is_app_running)
+}
@@ -0,0 +1,35 @@
+use color_eyre::eyre::Result;
+use postform_cli::{init, print_version, Command};
+use structopt::StructOpt;
+
+const BIN_NAME: &str = env!("CARGO_BIN_NAME");
+
+/// Decodes the logs of Postform, an efficient logging framework for mcu's.
+#[derive(Debug, StructOpt)]
+#[structopt(name = BIN_NAME)]
+struct Opts {
+    /// Shows the version information.
+    #[structopt(long, short = "V")]
+    version: bool,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+fn main() -> Result<()> {
+    init()?;
+
+    let opts = Opts::from_args();
+    match opts.command {
+        Some(command) => command.run(BIN_NAME),
+        None if opts.version => {
+            print_version(BIN_NAME);
+            Ok(())
+        }
+        None => {
+            Opts::clap().print_help()?;
+            println!();
+            Ok(())
+        }
+    }
+}
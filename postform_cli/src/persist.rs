@@ -0,0 +1,108 @@
+use crate::{CommonOpts, Printer};
+use color_eyre::eyre::Result;
+use postform_decoder::{ElfMetadata, Log, PersistDecoder};
+use std::{
+    fs,
+    io::{self, Read, Seek},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+use structopt::StructOpt;
+
+fn print_result(log: Result<Log, postform_decoder::Error>, printer: &Printer) {
+    printer.print_log(log.map_err(|error| format!("Error parsing log: {}", error)));
+}
+
+/// Decodes logs as they are read from the stream, until the end of the stream.
+fn decode_log_stream<R: Read>(reader: R, elf_metadata: &ElfMetadata, printer: &Printer) {
+    for log in PersistDecoder::new(elf_metadata, reader) {
+        print_result(log, printer);
+    }
+}
+
+/// Identifies a file, so that rotations of the log file can be detected.
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Decodes all logs in the file and keeps waiting for new ones, reopening the file if it is
+/// truncated or replaced by a new one.
+fn follow_log_file(path: &Path, elf_metadata: &ElfMetadata, printer: &Printer) -> Result<()> {
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    let log_file = fs::File::open(path)?;
+    let mut current_id = file_id(&log_file.metadata()?);
+    let mut decoder = PersistDecoder::new(elf_metadata, log_file);
+    loop {
+        while let Some(log) = decoder.next_available() {
+            print_result(log, printer);
+        }
+        thread::sleep(POLL_INTERVAL);
+
+        // The file may be missing for a moment while it is rotated
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        let position = decoder.get_mut().stream_position()?;
+        let rotated = file_id(&metadata) != current_id;
+        if rotated || metadata.len() < position {
+            // Drain anything written to the old file before switching over
+            while let Some(log) = decoder.next_available() {
+                print_result(log, printer);
+            }
+            log::info!(
+                "Log file {}, reopening it",
+                if rotated { "rotated" } else { "truncated" }
+            );
+            let log_file = fs::File::open(path)?;
+            current_id = file_id(&log_file.metadata()?);
+            decoder.reset(log_file);
+        }
+    }
+}
+
+/// Reads logs written to a file by a FileLogger.
+#[derive(Debug, StructOpt)]
+#[structopt(group = CommonOpts::elf_group(&[]))]
+pub struct Opts {
+    #[structopt(flatten)]
+    pub common: CommonOpts,
+
+    /// Path to the binary log file. The logs are read from stdin if the path is "-" or not given.
+    #[structopt(name = "LOG_FILE", parse(from_os_str))]
+    log_file: Option<PathBuf>,
+
+    /// Keeps waiting for new logs appended to the log file after decoding the existing ones.
+    /// Handles truncation and rotation of the log file. Logs read from stdin are always decoded
+    /// until the end of the stream.
+    #[structopt(long, short = "f")]
+    follow: bool,
+}
+
+/// Runs the persist command.
+pub fn run(opts: Opts) -> Result<()> {
+    let elf_metadata = opts.common.elf_metadata()?;
+
+    let printer = opts.common.printer(false);
+    match opts.log_file.filter(|path| path != Path::new("-")) {
+        Some(log_path) if opts.follow => follow_log_file(&log_path, &elf_metadata, &printer),
+        Some(log_path) => {
+            let log_file = io::BufReader::new(fs::File::open(log_path)?);
+            decode_log_stream(log_file, &elf_metadata, &printer);
+            Ok(())
+        }
+        None => {
+            decode_log_stream(io::stdin().lock(), &elf_metadata, &printer);
+            Ok(())
+        }
+    }
+}
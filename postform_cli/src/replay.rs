@@ -0,0 +1,99 @@
+use crate::{install_ctrlc_handler, report_error, CommonOpts, FramingOpts};
+use color_eyre::eyre::Result;
+use postform_decoder::{elf_hash, CaptureReader};
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+use structopt::StructOpt;
+
+/// Longest sleep while pacing the replay, so that the user can still interrupt it.
+const MAX_SLEEP: Duration = Duration::from_millis(100);
+
+/// Sleeps until `deadline`, returning early if the user requests to exit.
+fn sleep_until(deadline: Instant, is_app_running: &AtomicBool) {
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || !is_app_running.load(Ordering::Relaxed) {
+            return;
+        }
+        thread::sleep(remaining.min(MAX_SLEEP));
+    }
+}
+
+/// Replays a capture recorded with --record, decoding it as if it was received from the target.
+///
+/// The ELF argument can be omitted if the capture embeds the metadata dictionary.
+#[derive(Debug, StructOpt)]
+#[structopt(group = CommonOpts::elf_group(&[]))]
+pub struct Opts {
+    #[structopt(flatten)]
+    pub common: CommonOpts,
+
+    /// Path to the capture file. If not given, the capture is the ELF argument.
+    #[structopt(name = "CAPTURE", parse(from_os_str))]
+    capture: Option<PathBuf>,
+
+    /// Replays the capture in real time, following the host timestamps stored with the data,
+    /// instead of as fast as possible.
+    #[structopt(long)]
+    realtime: bool,
+
+    #[structopt(flatten)]
+    framing: FramingOpts,
+}
+
+/// Runs the replay command.
+pub fn run(opts: Opts) -> Result<()> {
+    let elf_metadata = opts.common.elf_metadata()?;
+    let mut decoder = opts.framing.serial_decoder(&elf_metadata);
+    let capture_path = opts.capture.as_deref().unwrap_or(opts.common.elf_path());
+    let reader = CaptureReader::new(BufReader::new(File::open(capture_path)?))?;
+
+    let header = reader.header();
+    log::info!(
+        "Replaying capture recorded through {} at {}",
+        header.transport,
+        humantime::format_rfc3339_millis(header.start_time)
+    );
+    if let Some(version) = &header.firmware_version {
+        log::info!("Firmware version: {}", version);
+    }
+    if opts.capture.is_some() && header.elf_hash != elf_hash(&fs::read(opts.common.elf_path())?) {
+        log::warn!("The capture was recorded with a different ELF file or dictionary");
+    }
+
+    let printer = opts.common.printer(false);
+    let is_app_running = install_ctrlc_handler()?;
+    let start = Instant::now();
+    let mut first_timestamp = None;
+    for chunk in reader {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(error) => {
+                report_error(format!("Error reading capture: {}", error));
+                break;
+            }
+        };
+
+        if opts.realtime {
+            let first_timestamp = *first_timestamp.get_or_insert(chunk.timestamp);
+            let offset = chunk
+                .timestamp
+                .duration_since(first_timestamp)
+                .unwrap_or_default();
+            sleep_until(start + offset, &is_app_running);
+        }
+        if !is_app_running.load(Ordering::Relaxed) {
+            break;
+        }
+
+        decoder.feed_and_do_at(&chunk.data, chunk.timestamp, |log| printer.print_log(log));
+    }
+    log::info!("Replay done. {:?}", decoder.stats());
+    Ok(())
+}
@@ -0,0 +1,630 @@
+use crate::target::{
+    attach_rtt, attach_rtt_with_timeout, configure_rtt_mode, disable_cdebugen, download_firmware,
+    rtt_scan_region, rtt_symbol_scan_region, run_core, LoggerLevel, RttMode, TargetLogLevel,
+};
+use crate::{
+    install_ctrlc_handler, report_error,
+    tcp::{self, Backoff, TcpSource},
+    CommonOpts, FramingOpts, RecordOpts,
+};
+use color_eyre::eyre::{eyre, Result};
+use object::read::File as ElfFile;
+use postform_decoder::{is_capture, ElfMetadata, OutputFormat, SerialDecoder};
+use probe_rs::{DebugProbeError, DebugProbeSelector, Probe, Session};
+use probe_rs_gdb_server::GdbInstanceConfiguration;
+use probe_rs_rtt::{Rtt, ScanRegion, UpChannel};
+use std::sync::atomic::Ordering;
+use std::{
+    fs,
+    io::{self, IsTerminal, Read},
+    str::FromStr,
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex, OnceLock,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+use structopt::StructOpt;
+use thiserror::Error;
+
+fn print_probes() {
+    let probes = Probe::list_all();
+
+    if !probes.is_empty() {
+        println!("The following devices were found:");
+        probes
+            .iter()
+            .enumerate()
+            .for_each(|(num, link)| println!("[{}]: {:?}", num, link));
+    } else {
+        println!("No devices were found.");
+    }
+}
+
+fn print_chips() {
+    let registry = probe_rs::config::families().expect("Could not retrieve chip family registry");
+    for chip_family in registry {
+        println!("{}", chip_family.name);
+        println!("    Variants:");
+        for variant in chip_family.variants.iter() {
+            println!("        {}", variant.name);
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+enum ProbeErrors {
+    #[error("There are no probes available")]
+    NoProbesAvailable,
+    #[error(
+        "More than one probe available. Select one of them with --probe-selector or --probe-index"
+    )]
+    MoreThanOneProbeAvailable,
+    #[error("Probe index is larger than the number of probes available")]
+    IndexOutOfRange,
+    #[error("Specific probe error from probe-rs")]
+    OpenError(#[source] Box<DebugProbeError>),
+}
+
+impl From<DebugProbeError> for ProbeErrors {
+    fn from(error: DebugProbeError) -> Self {
+        ProbeErrors::OpenError(Box::new(error))
+    }
+}
+
+/// Opens a probe with the given index or the first one if there is only one
+fn open_probe(index: Option<usize>) -> Result<Probe, ProbeErrors> {
+    let probes = Probe::list_all();
+    if probes.is_empty() {
+        return Err(ProbeErrors::NoProbesAvailable);
+    }
+
+    let index = match index {
+        Some(index) if (index >= probes.len()) => {
+            return Err(ProbeErrors::IndexOutOfRange);
+        }
+        None if (probes.len() != 1) => {
+            return Err(ProbeErrors::MoreThanOneProbeAvailable);
+        }
+        Some(index) => index,
+        None => 0,
+    };
+
+    Ok(Probe::open(probes[index].clone())?)
+}
+
+/// Default port of the RTT TCP servers of OpenOCD and the J-Link GDB server.
+const DEFAULT_RTT_SERVER_PORT: u16 = 19021;
+
+/// Appends the default RTT server port to the address if it doesn't have one. IPv6 addresses
+/// must be enclosed in brackets.
+fn rtt_server_address(address: &str) -> String {
+    match address.rsplit_once(':') {
+        Some((_, port)) if port.parse::<u16>().is_ok() => address.to_owned(),
+        _ => format!("{}:{}", address, DEFAULT_RTT_SERVER_PORT),
+    }
+}
+
+/// Returns the label of an RTT channel, which is its name or its number if it has none.
+fn channel_label(number: usize, name: Option<&str>) -> String {
+    match name {
+        Some(name) if !name.is_empty() => name.to_owned(),
+        _ => number.to_string(),
+    }
+}
+
+/// RTT control block of a core, given as CORE:SYMBOL in the command line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RttBlock {
+    core: usize,
+    /// Symbol of the control block, or None to use the default one.
+    symbol: Option<String>,
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid RTT block \"{0}\", expected CORE:SYMBOL")]
+struct InvalidRttBlock(String);
+
+impl FromStr for RttBlock {
+    type Err = InvalidRttBlock;
+
+    fn from_str(block: &str) -> Result<Self, Self::Err> {
+        let (core, symbol) = block
+            .split_once(':')
+            .ok_or_else(|| InvalidRttBlock(block.to_owned()))?;
+        let core = core
+            .parse()
+            .map_err(|_| InvalidRttBlock(block.to_owned()))?;
+        if symbol.is_empty() {
+            return Err(InvalidRttBlock(block.to_owned()));
+        }
+        Ok(Self {
+            core,
+            symbol: Some(symbol.to_owned()),
+        })
+    }
+}
+
+/// RTT up channel of a core with its own decoder, so that the logs of each channel are decoded
+/// independently.
+struct ChannelDecoder<'a> {
+    core: usize,
+    channel: UpChannel,
+    decoder: SerialDecoder<'a>,
+}
+
+/// Parses a level typed by the user, either its name or its first letter.
+fn parse_level_input(input: &str) -> Option<TargetLogLevel> {
+    match input.trim().to_ascii_lowercase().as_str() {
+        "d" => Some(TargetLogLevel::Debug),
+        "i" => Some(TargetLogLevel::Info),
+        "w" => Some(TargetLogLevel::Warning),
+        "e" => Some(TargetLogLevel::Error),
+        "o" => Some(TargetLogLevel::Off),
+        input => input.parse().ok(),
+    }
+}
+
+/// Disables the line buffering and echo of stdin, so that each key is read as soon as it is
+/// pressed. The original settings are restored when the process exits. Returns false if stdin is
+/// not a terminal.
+#[cfg(unix)]
+fn enable_keypress_input() -> bool {
+    static ORIGINAL_TERMIOS: OnceLock<libc::termios> = OnceLock::new();
+
+    extern "C" fn restore_terminal() {
+        if let Some(termios) = ORIGINAL_TERMIOS.get() {
+            // SAFETY: the settings were read from the same file descriptor by tcgetattr
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, termios) };
+        }
+    }
+
+    if !io::stdin().is_terminal() {
+        return false;
+    }
+    // SAFETY: termios is a plain C struct, fully written by tcgetattr when it succeeds
+    let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+    if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } != 0 {
+        return false;
+    }
+    if ORIGINAL_TERMIOS.set(termios).is_ok() {
+        // SAFETY: restore_terminal only accesses the settings stored above
+        unsafe { libc::atexit(restore_terminal) };
+    }
+
+    // Signals are still generated, so that ctrl-c exits as usual
+    termios.c_lflag &= !(libc::ICANON | libc::ECHO);
+    termios.c_cc[libc::VMIN] = 1;
+    termios.c_cc[libc::VTIME] = 0;
+    // SAFETY: termios holds valid settings, as read by tcgetattr
+    unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios) == 0 }
+}
+
+#[cfg(not(unix))]
+fn enable_keypress_input() -> bool {
+    false
+}
+
+/// Reads the levels selected by the user in stdin from a separate thread. In a terminal, a level
+/// is selected as soon as its key is pressed. Otherwise, stdin is read line by line.
+fn spawn_level_input() -> Receiver<TargetLogLevel> {
+    let (sender, receiver) = mpsc::channel();
+    let keypress = enable_keypress_input();
+    thread::spawn(move || {
+        let (inputs, hint): (Box<dyn Iterator<Item = String>>, _) = if keypress {
+            let keys = io::stdin().lock().bytes().map_while(Result::ok);
+            (
+                Box::new(keys.map(|key| char::from(key).to_string())),
+                "Press d, i, w, e or o",
+            )
+        } else {
+            let lines = io::stdin().lines().map_while(Result::ok);
+            (Box::new(lines), "Type d, i, w, e or o followed by enter")
+        };
+        for input in inputs {
+            if input.trim().is_empty() {
+                continue;
+            }
+            match parse_level_input(&input) {
+                Some(level) => {
+                    if sender.send(level).is_err() {
+                        break;
+                    }
+                }
+                None => report_error(format!("Unknown level \"{}\". {}", input.trim(), hint)),
+            }
+        }
+    });
+    receiver
+}
+
+/// Sets the mode of the given up channels of an RTT control block, accessed through its core.
+fn set_channels_mode(
+    session: &Arc<Mutex<Session>>,
+    core_index: usize,
+    rtt: &Rtt,
+    channels: &[usize],
+    mode: RttMode,
+) -> Result<()> {
+    for &channel in channels {
+        configure_rtt_mode(session.clone(), core_index, rtt.ptr() as u64, channel, mode)?;
+    }
+    Ok(())
+}
+
+/// Writes the level to all the loggers in the target, accessing its memory through the given core.
+fn set_logger_levels(
+    session: &Mutex<Session>,
+    core_index: usize,
+    loggers: &[LoggerLevel],
+    level: TargetLogLevel,
+) -> Result<()> {
+    let mut locked_session = session.lock().unwrap();
+    let mut core = locked_session.core(core_index)?;
+    for logger in loggers {
+        logger.write(&mut core, level)?;
+    }
+    Ok(())
+}
+
+/// Reads logs from the target through RTT using a debug probe.
+#[derive(Debug, StructOpt)]
+#[structopt(group = CommonOpts::elf_group(&["list-chips", "list-probes"]))]
+pub struct Opts {
+    #[structopt(flatten)]
+    pub common: CommonOpts,
+
+    /// List supported chips and exit.
+    #[structopt(long)]
+    list_chips: bool,
+
+    /// Lists all the connected probes and exit.
+    #[structopt(long)]
+    list_probes: bool,
+
+    /// The chip.
+    #[structopt(long, required_unless_one(&["list-chips", "list-probes", "version", "rtt-server"]), env = "POSTFORM_CHIP")]
+    chip: Option<String>,
+
+    /// The probe to open. The format is <VID>:<PID>[:<SERIAL>].
+    #[structopt(long, env = "POSTFORM_PROBE")]
+    probe_selector: Option<DebugProbeSelector>,
+
+    /// Index of the probe to open. Can be obtained with --list-probes.
+    #[structopt(long = "probe-index")]
+    probe_index: Option<usize>,
+
+    /// Attaches to a running target instead of downloading the firmware. Required when using a
+    /// metadata dictionary, and the RTT control block is then searched for in the target RAM.
+    #[structopt(long, short)]
+    attach: bool,
+
+    #[structopt(long, short)]
+    gdb_server: bool,
+
+    /// Core running the firmware, which is halted and resumed when downloading it and whose RTT
+    /// control block is read unless --rtt-block is given.
+    #[structopt(long, default_value = "0")]
+    core: usize,
+
+    /// RTT control block of a core in multicore targets, given as CORE:SYMBOL, like
+    /// "1:_SEGGER_RTT_CM4". Can be given once per core to decode the logs of all of them,
+    /// labeling each log with its core.
+    #[structopt(long = "rtt-block", number_of_values = 1)]
+    rtt_blocks: Vec<RttBlock>,
+
+    /// Time to wait for the RTT control blocks of the cores other than --core, which are looked
+    /// up once the firmware runs, since it usually starts the other cores.
+    #[structopt(long, default_value = "5000")]
+    rtt_block_timeout_ms: u64,
+
+    /// RTT up channel to decode, 0 by default. Can be given multiple times to decode several
+    /// channels at once, labeling each log with the name of its channel.
+    #[structopt(long, short, number_of_values = 1)]
+    channel: Vec<usize>,
+
+    /// Reads the logs from the RTT TCP server of OpenOCD or a J-Link GDB server instead of
+    /// opening a probe, so that they can be decoded alongside a debugging session. The address
+    /// is given as host[:port], with port 19021 by default. The server forwards a single RTT
+    /// channel, which is selected when starting it.
+    #[structopt(long, conflicts_with_all(&["gdb-server", "channel", "loggers", "rtt-blocks"]))]
+    rtt_server: Option<String>,
+
+    /// Symbol name of a logger in the firmware whose level can be changed at runtime, like
+    /// "logger" or "app::logger". Can be given multiple times. While running, press d, i, w, e or
+    /// o (off) to set the level of all the loggers. If stdin is not a terminal, levels are read
+    /// line by line instead. Keys are not read with --format json. Press ctrl-c to exit.
+    #[structopt(long = "logger", number_of_values = 1)]
+    loggers: Vec<String>,
+
+    /// Level written to the loggers at startup: "debug", "info", "warning", "error" or "off".
+    /// The firmware can still override it by calling setLevel.
+    #[structopt(long, requires("loggers"))]
+    target_level: Option<TargetLogLevel>,
+
+    #[structopt(flatten)]
+    framing: FramingOpts,
+
+    #[structopt(flatten)]
+    record: RecordOpts,
+}
+
+/// Runs the rtt command.
+pub fn run(opts: Opts) -> Result<()> {
+    if opts.list_probes {
+        print_probes();
+        return Ok(());
+    }
+
+    if opts.list_chips {
+        print_chips();
+        return Ok(());
+    }
+
+    if let Some(address) = &opts.rtt_server {
+        let elf_metadata = opts.common.elf_metadata()?;
+        let mut decoder = opts.framing.serial_decoder(&elf_metadata);
+        let mut source = TcpSource::new(&rtt_server_address(address), Backoff::default());
+        let mut recorder = opts
+            .record
+            .recorder(&opts.common, &elf_metadata, "rtt-server")?;
+        return tcp::decode_stream(
+            &mut source,
+            &mut decoder,
+            &mut recorder,
+            &opts.common.printer(false),
+        );
+    }
+
+    let rtt_channels = if opts.channel.is_empty() {
+        vec![0]
+    } else {
+        opts.channel.clone()
+    };
+    let rtt_blocks = if opts.rtt_blocks.is_empty() {
+        vec![RttBlock {
+            core: opts.core,
+            symbol: None,
+        }]
+    } else {
+        opts.rtt_blocks.clone()
+    };
+    if rtt_channels.len() * rtt_blocks.len() > 1 && opts.record.record.is_some() {
+        return Err(eyre!(
+            "Recording is only supported for a single RTT channel and core"
+        ));
+    }
+
+    let elf_contents = fs::read(opts.common.elf_path())?;
+    let elf_metadata = ElfMetadata::from_bytes(&elf_contents, opts.common.disable_version_check)?;
+    let elf_file = if ElfMetadata::is_dictionary(&elf_contents) || is_capture(&elf_contents) {
+        if !opts.attach {
+            return Err(eyre!(
+                "Downloading the firmware requires an ELF file, use --attach with a dictionary or capture"
+            ));
+        }
+        None
+    } else {
+        Some(ElfFile::parse(&elf_contents[..])?)
+    };
+    let loggers = match &elf_file {
+        Some(elf_file) => opts
+            .loggers
+            .iter()
+            .map(|name| LoggerLevel::find(elf_file, name))
+            .collect::<Result<Vec<_>>>()?,
+        None if !opts.loggers.is_empty() => {
+            return Err(eyre!("Finding the loggers requires an ELF file"));
+        }
+        None => vec![],
+    };
+
+    let probe = if let Some(probe_name) = opts.probe_selector {
+        Probe::open(probe_name)?
+    } else {
+        open_probe(opts.probe_index)?
+    };
+
+    if let Some(chip) = opts.chip {
+        let session = Arc::new(Mutex::new(
+            probe.attach(chip, probe_rs::Permissions::new())?,
+        ));
+
+        let is_app_running = install_ctrlc_handler()?;
+        if !opts.attach {
+            download_firmware(&session, opts.common.elf_path(), opts.core)?;
+        }
+
+        let scan_region = |block: &RttBlock| match (&elf_file, &block.symbol) {
+            (Some(elf_file), Some(symbol)) => rtt_symbol_scan_region(elf_file, symbol),
+            (Some(elf_file), None) => rtt_scan_region(elf_file),
+            (None, Some(_)) => Err(eyre!(
+                "Finding the RTT blocks by symbol requires an ELF file"
+            )),
+            (None, None) => Ok(ScanRegion::Ram),
+        };
+        // The other cores are usually started by the firmware of the selected one, so their
+        // control blocks are only initialized once it runs
+        let (blocks, secondary_blocks): (Vec<_>, Vec<_>) =
+            rtt_blocks.iter().partition(|block| block.core == opts.core);
+
+        let mut rtts = vec![];
+        for block in blocks {
+            let rtt = attach_rtt(session.clone(), block.core, &scan_region(block)?)?;
+            set_channels_mode(&session, block.core, &rtt, &rtt_channels, RttMode::Blocking)?;
+            rtts.push((block.core, rtt));
+        }
+        if let Some(level) = opts.target_level {
+            set_logger_levels(&session, opts.core, &loggers, level)?;
+        }
+        // The JSON output is meant for other programs, which don't select levels interactively
+        let interactive = opts.common.format == OutputFormat::Text;
+        let level_input = (interactive && !loggers.is_empty()).then(spawn_level_input);
+        if !opts.attach {
+            run_core(session.clone(), opts.core)?;
+        }
+        for block in secondary_blocks {
+            let rtt = attach_rtt_with_timeout(
+                session.clone(),
+                block.core,
+                &scan_region(block)?,
+                Duration::from_millis(opts.rtt_block_timeout_ms),
+            )?;
+            set_channels_mode(&session, block.core, &rtt, &rtt_channels, RttMode::Blocking)?;
+            rtts.push((block.core, rtt));
+        }
+
+        if !opts.gdb_server {
+            for (core, _) in &rtts {
+                disable_cdebugen(session.clone(), *core)?;
+            }
+        } else {
+            let session = session.clone();
+            let _ = Some(std::thread::spawn(move || {
+                let gdb_connection_string = "127.0.0.1:1337";
+                // This next unwrap will always resolve as the connection string is always Some(T).
+                log::info!("Firing up GDB stub at {}.", gdb_connection_string);
+                let config = {
+                    let locked_session = session.lock().unwrap();
+                    GdbInstanceConfiguration::from_session(
+                        &locked_session,
+                        Some(gdb_connection_string),
+                    )
+                };
+                if let Err(e) = probe_rs_gdb_server::run(&session, config.iter()) {
+                    log::error!("During the execution of GDB an error was encountered:");
+                    log::error!("{:?}", e);
+                }
+            }));
+        }
+
+        let mut channel_decoders = vec![];
+        for (core, rtt) in &mut rtts {
+            for &number in &rtt_channels {
+                let channel = rtt
+                    .up_channels()
+                    .take(number)
+                    .ok_or_else(|| eyre!("RTT up channel {} not found in core {}", number, core))?;
+                let mut decoder = opts.framing.serial_decoder(&elf_metadata);
+                if rtt_channels.len() > 1 {
+                    decoder.set_channel(Some(channel_label(number, channel.name())));
+                }
+                decoder.set_core(Some(*core));
+                channel_decoders.push(ChannelDecoder {
+                    core: *core,
+                    channel,
+                    decoder,
+                });
+            }
+        }
+
+        let mut buffer = [0u8; 1024];
+        let mut recorder = opts.record.recorder(&opts.common, &elf_metadata, "rtt")?;
+        let printer = opts.common.printer(rtt_blocks.len() > 1);
+        loop {
+            for ChannelDecoder {
+                core,
+                channel,
+                decoder,
+            } in &mut channel_decoders
+            {
+                let count = {
+                    let mut locked_session = session.lock().unwrap();
+                    let mut core = locked_session.core(*core)?;
+                    channel.read(&mut core, &mut buffer[..])?
+                };
+                if count > 0 {
+                    let received = SystemTime::now();
+                    recorder.record(received, &buffer[..count])?;
+                    decoder
+                        .feed_and_do_at(&buffer[..count], received, |log| printer.print_log(log));
+                }
+            }
+
+            if let Some(level_input) = &level_input {
+                for level in level_input.try_iter() {
+                    if let Err(error) = set_logger_levels(&session, opts.core, &loggers, level) {
+                        report_error(format!("Error setting the log level: {}", error));
+                    }
+                }
+            }
+
+            // Close application if requested
+            if !is_app_running.load(Ordering::Relaxed) {
+                for ChannelDecoder {
+                    core,
+                    channel,
+                    decoder,
+                } in &channel_decoders
+                {
+                    log::info!(
+                        "Closing channel {} of core {}. {:?}",
+                        channel_label(channel.number(), channel.name()),
+                        core,
+                        decoder.stats()
+                    );
+                }
+                break;
+            }
+        }
+        for (core, rtt) in &rtts {
+            set_channels_mode(&session, *core, rtt, &rtt_channels, RttMode::NonBlocking)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rtt_server_address() {
+        assert_eq!(rtt_server_address("localhost"), "localhost:19021");
+        assert_eq!(rtt_server_address("localhost:9090"), "localhost:9090");
+        assert_eq!(rtt_server_address("10.0.0.2"), "10.0.0.2:19021");
+        assert_eq!(rtt_server_address("[::1]"), "[::1]:19021");
+        assert_eq!(rtt_server_address("[::1]:9090"), "[::1]:9090");
+    }
+
+    #[test]
+    fn test_rtt_block_from_str() {
+        assert_eq!(
+            "1:_SEGGER_RTT_CM4".parse::<RttBlock>().unwrap(),
+            RttBlock {
+                core: 1,
+                symbol: Some("_SEGGER_RTT_CM4".to_owned())
+            }
+        );
+        assert!("_SEGGER_RTT".parse::<RttBlock>().is_err());
+        assert!("x:_SEGGER_RTT".parse::<RttBlock>().is_err());
+        assert!("1:".parse::<RttBlock>().is_err());
+    }
+
+    #[test]
+    fn test_channel_label() {
+        assert_eq!(channel_label(0, Some("Terminal")), "Terminal");
+        assert_eq!(channel_label(1, Some("")), "1");
+        assert_eq!(channel_label(2, None), "2");
+    }
+
+    #[test]
+    fn test_parse_level_input() {
+        assert_eq!(parse_level_input("d"), Some(TargetLogLevel::Debug));
+        assert_eq!(parse_level_input("W\n"), Some(TargetLogLevel::Warning));
+        assert_eq!(parse_level_input(" error "), Some(TargetLogLevel::Error));
+        assert_eq!(parse_level_input("o"), Some(TargetLogLevel::Off));
+        assert_eq!(parse_level_input("x"), None);
+        assert_eq!(parse_level_input("verbose"), None);
+    }
+    #[test]
+    fn test_elf_argument_exemptions() {
+        let parse = |args: &[&str]| Opts::from_iter_safe(["postform_rtt"].iter().chain(args));
+        assert!(parse(&["--list-chips"]).is_ok());
+        assert!(parse(&["--list-probes"]).is_ok());
+        assert!(parse(&["--version"]).is_ok());
+        assert!(parse(&["--chip", "nRF52840_xxAA", "firmware.elf"]).is_ok());
+        assert!(parse(&["--chip", "nRF52840_xxAA"]).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{install_ctrlc_handler, CommonOpts, FramingOpts, RecordOpts};
+use color_eyre::eyre::Result;
+use serialport::{self, FlowControl, Parity, StopBits};
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, SystemTime},
+};
+use structopt::{clap::ArgGroup, StructOpt};
+
+/// Serial errors for Postform serial
+#[derive(Debug, thiserror::Error)]
+pub enum SerialError {
+    #[error("Invalid parity requested \"{0}\"")]
+    InvalidParityString(String),
+    #[error("Invalid stop bits requested \"{0}\"")]
+    InvalidStopBitsString(String),
+}
+
+fn try_to_serial_parity(parity: &str) -> Result<Parity, SerialError> {
+    match parity {
+        "odd" => Ok(Parity::Odd),
+        "even" => Ok(Parity::Even),
+        "none" => Ok(Parity::None),
+        _ => Err(SerialError::InvalidParityString(parity.to_owned())),
+    }
+}
+
+fn try_to_serial_stop_bits(stop_bits: &str) -> Result<StopBits, SerialError> {
+    match stop_bits {
+        "1" => Ok(StopBits::One),
+        "2" => Ok(StopBits::Two),
+        _ => Err(SerialError::InvalidStopBitsString(stop_bits.to_owned())),
+    }
+}
+
+/// Reads logs sent by a SerialLogger through a serial port.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    group = CommonOpts::elf_group(&["list-ports"]),
+    group = ArgGroup::with_name("port-or-exemption")
+        .args(&["port", "version", "list-ports"])
+        .multiple(true)
+        .required(true)
+)]
+pub struct Opts {
+    #[structopt(flatten)]
+    pub common: CommonOpts,
+
+    /// Serial port to read the logs from.
+    #[structopt(name = "port")]
+    port: Option<String>,
+
+    /// Serial port baudrate. Defaults to 115200.
+    #[structopt(long, short = "s")]
+    baudrate: Option<u32>,
+
+    /// Serial port stop bits number. Defaults to 1.
+    #[structopt(long, parse(try_from_str=try_to_serial_stop_bits))]
+    stop_bits: Option<StopBits>,
+
+    /// Serial port parity configuration. Defaults to None.
+    #[structopt(long, parse(try_from_str=try_to_serial_parity))]
+    parity: Option<Parity>,
+
+    /// Lists the available serial ports and exits.
+    #[structopt(long)]
+    list_ports: bool,
+
+    #[structopt(flatten)]
+    framing: FramingOpts,
+
+    #[structopt(flatten)]
+    record: RecordOpts,
+}
+
+/// Runs the serial command.
+pub fn run(opts: Opts) -> Result<()> {
+    if opts.list_ports {
+        let available_ports = serialport::available_ports()?;
+        for port in available_ports {
+            println!("{:?}", port);
+        }
+        return Ok(());
+    }
+
+    let elf_metadata = opts.common.elf_metadata()?;
+    let mut decoder = opts.framing.serial_decoder(&elf_metadata);
+    let mut recorder = opts
+        .record
+        .recorder(&opts.common, &elf_metadata, "serial")?;
+
+    let mut port = serialport::new(opts.port.unwrap(), opts.baudrate.unwrap_or(115200u32))
+        .parity(opts.parity.unwrap_or(Parity::None))
+        .stop_bits(opts.stop_bits.unwrap_or(StopBits::One))
+        .flow_control(FlowControl::None)
+        .open()?;
+    port.set_timeout(Duration::from_millis(100))?;
+
+    let printer = opts.common.printer(false);
+    let is_app_running = install_ctrlc_handler()?;
+    while is_app_running.load(Ordering::Relaxed) {
+        let mut buffer = [0; 1024];
+        let count = match port.read(&mut buffer[..]) {
+            Ok(count) => Ok(count),
+            Err(error) if error.kind() == std::io::ErrorKind::TimedOut => Ok(0),
+            Err(error) => Err(error),
+        }?;
+
+        if count > 0 {
+            let received = SystemTime::now();
+            recorder.record(received, &buffer[..count])?;
+            decoder.feed_and_do_at(&buffer[..count], received, |log| printer.print_log(log));
+        }
+    }
+    log::info!("Closing application. {:?}", decoder.stats());
+    Ok(())
+}
@@ -0,0 +1,464 @@
+use color_eyre::eyre::Result;
+use field_offset::offset_of;
+use object::read::{File as ElfFile, Object, ObjectSymbol};
+use probe_rs::{
+    flashing::{download_file, Format},
+    MemoryInterface, Session,
+};
+use probe_rs_rtt::{Rtt, ScanRegion};
+use std::{
+    fs,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// RTT Errors for Postform Rtt
+#[derive(Debug, thiserror::Error)]
+pub enum RttError {
+    #[error("Missing symbol {0}")]
+    MissingSymbol(String),
+    #[error("Logger {0} not found in the ELF file")]
+    MissingLogger(String),
+    #[error("Invalid log level \"{0}\"")]
+    InvalidLogLevel(String),
+    #[error("Invalid log level {1} read from logger {0}")]
+    InvalidTargetLogLevel(String, u32),
+}
+
+#[repr(C)]
+struct RttHeader {
+    id: [u8; 16],
+    max_up_channels: u32,
+    max_down_channels: u32,
+}
+
+#[repr(C)]
+struct RttChannel {
+    name: u32,
+    buffer: u32,
+    size: u32,
+    write: u32,
+    read: u32,
+    flags: u32,
+}
+
+/// Downloads a FW ELF to the target in the associated session, halting the given core at main.
+pub fn download_firmware(
+    session: &Arc<Mutex<Session>>,
+    elf_path: &Path,
+    core_index: usize,
+) -> Result<()> {
+    let mut mutex_guard = session.lock().unwrap();
+    log::info!("Loading FW to target");
+    download_file(&mut mutex_guard, elf_path, Format::Elf)?;
+    log::info!("Download complete!");
+
+    let file_contents = fs::read(elf_path)?;
+    let elf_file = ElfFile::parse(&file_contents[..])?;
+    let main = elf_file
+        .symbols()
+        .find(|s| s.name().unwrap() == "main")
+        .ok_or_else(|| RttError::MissingSymbol("main".to_owned()))?;
+
+    let mut core = mutex_guard.core(core_index)?;
+    let _ = core.reset_and_halt(Duration::from_millis(100))?;
+    // If the main address has bit 0 set to indicate thumb mode in an ARM binary, let's set it back
+    // to 0
+    let main = main.address() & !0x01_u64;
+    core.set_hw_breakpoint(main)?;
+    log::debug!("Inserting breakpoint at main() @ 0x{:x}", main);
+    core.run()?;
+    core.wait_for_core_halted(Duration::from_secs(1))?;
+    log::debug!("Core halted at main()");
+
+    Ok(())
+}
+
+/// Selects the execution mode for RTT in the target.
+#[derive(Copy, Clone, Debug)]
+pub enum RttMode {
+    /// The target does not block when the buffer is full, overflowing it.
+    NonBlocking = 1,
+    /// The target blocks until the buffer is ready to receive more data.
+    Blocking = 2,
+}
+
+/// Configures the selected RTT mode in the RTT control block at the given address, accessing the
+/// memory through the given core.
+pub fn configure_rtt_mode(
+    session: Arc<Mutex<Session>>,
+    core_index: usize,
+    rtt_addr: u64,
+    channel_idx: usize,
+    mode: RttMode,
+) -> Result<()> {
+    const CHANNEL_SIZE: usize = std::mem::size_of::<RttChannel>();
+    // Header size is: 16 bytes for the rtt magic string
+    //                 + 2 x uint32_t for number of up and down channels
+    const HEADER_SIZE: usize = std::mem::size_of::<RttHeader>();
+    let mut session_lock = session.lock().unwrap();
+    let mut core = session_lock.core(core_index)?;
+    let mode_flags_addr = rtt_addr as usize
+        + HEADER_SIZE
+        + CHANNEL_SIZE * channel_idx
+        + offset_of!(RttChannel => flags).get_byte_offset();
+    log::info!("Setting mode to {:?}", mode);
+    core.write_word_32(mode_flags_addr as u64, mode as u32)?;
+
+    Ok(())
+}
+
+/// Runs the given core and clears all its breakpoints
+pub fn run_core(session: Arc<Mutex<Session>>, core_index: usize) -> Result<()> {
+    let mut session_lock = session.lock().unwrap();
+    let mut core = session_lock.core(core_index)?;
+    log::info!("Clearing breakpoints and entering run state");
+    core.clear_all_hw_breakpoints()?;
+    core.run()?;
+
+    Ok(())
+}
+
+/// Disables C_DEBUGEN for the given core of a cortex-m mcu
+pub fn disable_cdebugen(session: Arc<Mutex<Session>>, core_index: usize) -> Result<()> {
+    let mut session_lock = session.lock().unwrap();
+    let mut core = session_lock.core(core_index)?;
+
+    // We write the DHCSR register here in order to disable C_DEBUGEN.
+    // Debugging the core is not possible while Postform is running, so at
+    // least we should be able to use the DebugMonitor Exception.
+    // We need to write the password to the DHCSR register in order for the
+    // write to be successful
+    let dhcsr_addr = 0xE000EDF0;
+    let dhcsr_val = [0xA05F << 16];
+    core.write_32(dhcsr_addr, &dhcsr_val)?;
+    log::info!("Disabled debugging of core {}", core_index);
+    Ok(())
+}
+
+/// Default symbol of the RTT control block.
+pub const DEFAULT_RTT_SYMBOL: &str = "_SEGGER_RTT";
+
+/// Returns the region where the RTT control block is located, which is the address of the
+/// `_SEGGER_RTT` symbol in the given ELF file.
+pub fn rtt_scan_region(elf_file: &ElfFile) -> Result<ScanRegion> {
+    rtt_symbol_scan_region(elf_file, DEFAULT_RTT_SYMBOL)
+}
+
+/// Returns the region where an RTT control block is located, which is the address of the given
+/// symbol in the ELF file. Used for targets with a control block per core.
+pub fn rtt_symbol_scan_region(elf_file: &ElfFile, symbol: &str) -> Result<ScanRegion> {
+    let rtt_block = elf_file
+        .symbols()
+        .find(|s| s.name() == Ok(symbol))
+        .ok_or_else(|| RttError::MissingSymbol(symbol.to_owned()))?;
+    Ok(ScanRegion::Exact(rtt_block.address() as u32))
+}
+
+/// Attaches to RTT through the given core, looking for the control block in the given scan region
+pub fn attach_rtt(
+    session: Arc<Mutex<Session>>,
+    core_index: usize,
+    scan_region: &ScanRegion,
+) -> Result<Rtt> {
+    log::info!(
+        "Attaching RTT of core {} in region {:x?}",
+        core_index,
+        scan_region
+    );
+    try_attach_rtt(&session, core_index, scan_region)
+}
+
+/// Attaches to RTT like [`attach_rtt`], retrying until the control block is found or the timeout
+/// expires. This is needed for the cores started by the firmware of another core, which
+/// initialize their control block some time after the firmware is run.
+pub fn attach_rtt_with_timeout(
+    session: Arc<Mutex<Session>>,
+    core_index: usize,
+    scan_region: &ScanRegion,
+    timeout: Duration,
+) -> Result<Rtt> {
+    log::info!(
+        "Waiting up to {:?} for the RTT of core {} in region {:x?}",
+        timeout,
+        core_index,
+        scan_region
+    );
+    retry_until(timeout, RTT_ATTACH_RETRY_INTERVAL, || {
+        try_attach_rtt(&session, core_index, scan_region)
+    })
+}
+
+const RTT_ATTACH_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+fn try_attach_rtt(
+    session: &Mutex<Session>,
+    core_index: usize,
+    scan_region: &ScanRegion,
+) -> Result<Rtt> {
+    let mut locked_session = session.lock().unwrap();
+    let memory_map = locked_session.target().memory_map.clone();
+    let mut core = locked_session.core(core_index)?;
+    Ok(Rtt::attach_region(&mut core, &memory_map[..], scan_region)?)
+}
+
+/// Calls `operation` every `interval` until it succeeds, returning its last error once `timeout`
+/// expires. The core may not even be accessible until it is started, so every error is retried.
+fn retry_until<T>(
+    timeout: Duration,
+    interval: Duration,
+    mut operation: impl FnMut() -> Result<T>,
+) -> Result<T> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match operation() {
+            Err(error) if Instant::now() < deadline => {
+                log::debug!("Retrying after error: {}", error);
+                thread::sleep(interval);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Log levels of a Postform logger in the target, with the values of `Postform::LogLevel`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TargetLogLevel {
+    Debug = 0,
+    Info = 1,
+    Warning = 2,
+    Error = 3,
+    /// No logs are sent.
+    Off = 4,
+}
+
+impl TargetLogLevel {
+    fn from_raw(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(TargetLogLevel::Debug),
+            1 => Some(TargetLogLevel::Info),
+            2 => Some(TargetLogLevel::Warning),
+            3 => Some(TargetLogLevel::Error),
+            4 => Some(TargetLogLevel::Off),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for TargetLogLevel {
+    type Err = RttError;
+
+    fn from_str(level: &str) -> Result<Self, Self::Err> {
+        match level.to_ascii_lowercase().as_str() {
+            "debug" => Ok(TargetLogLevel::Debug),
+            "info" => Ok(TargetLogLevel::Info),
+            "warning" | "warn" => Ok(TargetLogLevel::Warning),
+            "error" => Ok(TargetLogLevel::Error),
+            "off" => Ok(TargetLogLevel::Off),
+            _ => Err(RttError::InvalidLogLevel(level.to_owned())),
+        }
+    }
+}
+
+/// Memory of the target, accessed through the debug probe.
+pub trait TargetMemory {
+    /// Reads the 32-bit word at the given address.
+    fn read_u32(&mut self, address: u64) -> Result<u32>;
+
+    /// Writes a 32-bit word to the given address.
+    fn write_u32(&mut self, address: u64, value: u32) -> Result<()>;
+}
+
+impl TargetMemory for probe_rs::Core<'_> {
+    fn read_u32(&mut self, address: u64) -> Result<u32> {
+        Ok(MemoryInterface::read_word_32(self, address)?)
+    }
+
+    fn write_u32(&mut self, address: u64, value: u32) -> Result<()> {
+        Ok(MemoryInterface::write_word_32(self, address, value)?)
+    }
+}
+
+/// Returns the Itanium mangled name of a C++ variable qualified with `::`, like
+/// `app::logger`. Unqualified names are returned as they are, like C++ compilers do for
+/// variables in the global namespace.
+fn mangled_variable_name(name: &str) -> String {
+    if !name.contains("::") {
+        return name.to_owned();
+    }
+    let components: String = name
+        .split("::")
+        .map(|component| format!("{}{}", component.len(), component))
+        .collect();
+    format!("_ZN{}E", components)
+}
+
+/// Level variable of a Postform logger in the target.
+///
+/// The level is the `std::atomic<LogLevel>` member of `Postform::Logger`, which is the only
+/// member of the base class of all loggers, so it is located at the address of the logger.
+#[derive(Clone, Debug)]
+pub struct LoggerLevel {
+    name: String,
+    address: u64,
+}
+
+impl LoggerLevel {
+    /// Finds the logger with the given symbol name in the ELF file. Names qualified with `::`
+    /// are looked up by their mangled name.
+    pub fn find(elf_file: &ElfFile, name: &str) -> Result<Self> {
+        let mangled_name = mangled_variable_name(name);
+        let symbol = elf_file
+            .symbols()
+            .find(|s| s.name() == Ok(name) || s.name() == Ok(&mangled_name))
+            .ok_or_else(|| RttError::MissingLogger(name.to_owned()))?;
+        Ok(Self {
+            name: name.to_owned(),
+            address: symbol.address(),
+        })
+    }
+
+    /// Name of the logger, as given to `find`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Address of the level variable in the target.
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// Reads the current level of the logger.
+    pub fn read(&self, memory: &mut dyn TargetMemory) -> Result<TargetLogLevel> {
+        let value = memory.read_u32(self.address)?;
+        Ok(TargetLogLevel::from_raw(value)
+            .ok_or_else(|| RttError::InvalidTargetLogLevel(self.name.clone(), value))?)
+    }
+
+    /// Sets the level of the logger. The level is read back afterwards, so that writes to the
+    /// wrong address are detected.
+    pub fn write(&self, memory: &mut dyn TargetMemory, level: TargetLogLevel) -> Result<()> {
+        log::info!("Setting the level of logger {} to {:?}", self.name, level);
+        memory.write_u32(self.address, level as u32)?;
+        let value = memory.read_u32(self.address)?;
+        if value != level as u32 {
+            return Err(RttError::InvalidTargetLogLevel(self.name.clone(), value).into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_eyre::eyre::eyre;
+    use std::collections::HashMap;
+
+    /// Target memory backed by a map of words. Reads of unwritten words return zero.
+    #[derive(Default)]
+    struct FakeMemory {
+        words: HashMap<u64, u32>,
+        read_only: bool,
+    }
+
+    impl TargetMemory for FakeMemory {
+        fn read_u32(&mut self, address: u64) -> Result<u32> {
+            Ok(self.words.get(&address).copied().unwrap_or(0))
+        }
+
+        fn write_u32(&mut self, address: u64, value: u32) -> Result<()> {
+            if !self.read_only {
+                self.words.insert(address, value);
+            }
+            Ok(())
+        }
+    }
+
+    fn logger_level(address: u64) -> LoggerLevel {
+        LoggerLevel {
+            name: "logger".to_owned(),
+            address,
+        }
+    }
+
+    #[test]
+    fn test_retry_until() {
+        let mut attempts = 0;
+        let result = retry_until(Duration::from_secs(10), Duration::ZERO, || {
+            attempts += 1;
+            if attempts < 3 {
+                Err(eyre!("control block not found"))
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let result: Result<()> =
+            retry_until(Duration::from_millis(20), Duration::from_millis(5), || {
+                Err(eyre!("control block not found"))
+            });
+        assert_eq!(result.unwrap_err().to_string(), "control block not found");
+    }
+
+    #[test]
+    fn test_mangled_variable_name() {
+        assert_eq!(mangled_variable_name("logger"), "logger");
+        assert_eq!(mangled_variable_name("app::logger"), "_ZN3app6loggerE");
+        assert_eq!(
+            mangled_variable_name("app::radio::logger"),
+            "_ZN3app5radio6loggerE"
+        );
+    }
+
+    #[test]
+    fn test_target_log_level_from_str() {
+        assert_eq!(
+            "debug".parse::<TargetLogLevel>().unwrap(),
+            TargetLogLevel::Debug
+        );
+        assert_eq!(
+            "WARN".parse::<TargetLogLevel>().unwrap(),
+            TargetLogLevel::Warning
+        );
+        assert_eq!(
+            "off".parse::<TargetLogLevel>().unwrap(),
+            TargetLogLevel::Off
+        );
+        assert!("verbose".parse::<TargetLogLevel>().is_err());
+    }
+
+    #[test]
+    fn test_logger_level_write() {
+        let mut memory = FakeMemory::default();
+        memory.words.insert(0x2000_0100, 0xdead_beef);
+        let level = logger_level(0x2000_0104);
+
+        assert_eq!(level.read(&mut memory).unwrap(), TargetLogLevel::Debug);
+        level.write(&mut memory, TargetLogLevel::Warning).unwrap();
+        assert_eq!(memory.words[&0x2000_0104], 2);
+        assert_eq!(level.read(&mut memory).unwrap(), TargetLogLevel::Warning);
+        level.write(&mut memory, TargetLogLevel::Off).unwrap();
+        assert_eq!(level.read(&mut memory).unwrap(), TargetLogLevel::Off);
+
+        // Neighbouring memory is left untouched
+        assert_eq!(memory.words[&0x2000_0100], 0xdead_beef);
+        assert_eq!(memory.words.len(), 2);
+    }
+
+    #[test]
+    fn test_logger_level_write_not_applied() {
+        let mut memory = FakeMemory {
+            read_only: true,
+            ..Default::default()
+        };
+        let level = logger_level(0x2000_0000);
+        assert!(level.write(&mut memory, TargetLogLevel::Error).is_err());
+
+        memory.words.insert(0x2000_0000, 7);
+        assert!(level.read(&mut memory).is_err());
+    }
+}
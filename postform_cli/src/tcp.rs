@@ -0,0 +1,311 @@
+use crate::{install_ctrlc_handler, CommonOpts, FramingOpts, Printer, RecordOpts, Recorder};
+use color_eyre::eyre::Result;
+use postform_decoder::SerialDecoder;
+use std::{
+    io::{self, Read},
+    net::{TcpStream, ToSocketAddrs},
+    sync::atomic::Ordering,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+use structopt::StructOpt;
+
+/// Time to block waiting for data, so that the caller can periodically check if it should exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Time to wait for a connection to be established. Remote servers may take longer than a poll
+/// interval to accept it.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Exponential backoff between reconnection attempts.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl Backoff {
+    /// Creates a backoff that starts waiting `initial` and doubles the delay after every failed
+    /// attempt, up to `max`.
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            current: initial,
+        }
+    }
+
+    /// Returns the delay before the next attempt and increases the following one.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        self.current = (self.current * 2).min(self.max);
+        delay
+    }
+
+    /// Goes back to the initial delay. Called once a connection succeeds.
+    pub fn reset(&mut self) {
+        self.current = self.initial;
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(250), Duration::from_secs(10))
+    }
+}
+
+/// Result of polling a TcpSource.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TcpEvent {
+    /// No data was received before the poll interval elapsed.
+    Idle,
+    /// A new connection was established. Any partial frame from a previous connection is stale.
+    Connected,
+    /// The given number of bytes were read into the buffer.
+    Data(usize),
+    /// The connection was closed or failed. A reconnection will be attempted after a delay.
+    Disconnected,
+}
+
+/// Byte stream read from a TCP server, like a network serial server or a simulator publishing its
+/// log UART. The connection is reestablished with an exponential backoff whenever it is lost.
+pub struct TcpSource {
+    address: String,
+    stream: Option<TcpStream>,
+    backoff: Backoff,
+    next_attempt: Instant,
+}
+
+impl TcpSource {
+    /// Creates a source for the server at `address`, in `host:port` form. The first connection
+    /// is attempted on the first poll.
+    pub fn new(address: &str, backoff: Backoff) -> Self {
+        Self {
+            address: address.to_owned(),
+            stream: None,
+            backoff,
+            next_attempt: Instant::now(),
+        }
+    }
+
+    /// Returns true if the source is currently connected to the server.
+    pub fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Waits for up to the poll interval for something to happen: a connection being
+    /// established, data being received or the connection being lost.
+    pub fn poll(&mut self, buffer: &mut [u8]) -> TcpEvent {
+        let stream = match &mut self.stream {
+            Some(stream) => stream,
+            None => return self.try_connect(),
+        };
+
+        match stream.read(buffer) {
+            Ok(0) => {
+                log::warn!("Connection to {} closed by the server", self.address);
+                self.disconnect()
+            }
+            Ok(count) => TcpEvent::Data(count),
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    io::ErrorKind::WouldBlock
+                        | io::ErrorKind::TimedOut
+                        | io::ErrorKind::Interrupted
+                ) =>
+            {
+                TcpEvent::Idle
+            }
+            Err(error) => {
+                log::warn!("Connection to {} failed: {}", self.address, error);
+                self.disconnect()
+            }
+        }
+    }
+
+    fn disconnect(&mut self) -> TcpEvent {
+        self.stream = None;
+        self.next_attempt = Instant::now() + self.backoff.next_delay();
+        TcpEvent::Disconnected
+    }
+
+    fn try_connect(&mut self) -> TcpEvent {
+        let remaining = self.next_attempt.saturating_duration_since(Instant::now());
+        if !remaining.is_zero() {
+            thread::sleep(remaining.min(POLL_INTERVAL));
+            return TcpEvent::Idle;
+        }
+
+        match self.connect() {
+            Ok(stream) => {
+                log::info!("Connected to {}", self.address);
+                self.stream = Some(stream);
+                self.backoff.reset();
+                TcpEvent::Connected
+            }
+            Err(error) => {
+                let delay = self.backoff.next_delay();
+                log::warn!(
+                    "Could not connect to {}: {}. Retrying in {:?}",
+                    self.address,
+                    error,
+                    delay
+                );
+                self.next_attempt = Instant::now() + delay;
+                TcpEvent::Idle
+            }
+        }
+    }
+
+    fn connect(&self) -> io::Result<TcpStream> {
+        let mut last_error = None;
+        for address in self.address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+                    return Ok(stream);
+                }
+                Err(error) => last_error = Some(error),
+            }
+        }
+        Err(last_error
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "address did not resolve")))
+    }
+}
+
+/// Reads logs sent by a SerialLogger from a TCP server, like a network serial server or a
+/// simulator.
+#[derive(Debug, StructOpt)]
+#[structopt(group = CommonOpts::elf_group(&[]))]
+pub struct Opts {
+    #[structopt(flatten)]
+    pub common: CommonOpts,
+
+    /// Address of the server, in host:port form.
+    #[structopt(name = "ADDRESS", required_unless("version"))]
+    address: Option<String>,
+
+    /// Delay before the first reconnection attempt in milliseconds. It doubles after every
+    /// failed attempt.
+    #[structopt(long, default_value = "250")]
+    reconnect_delay_ms: u64,
+
+    /// Maximum delay between reconnection attempts in milliseconds.
+    #[structopt(long, default_value = "10000")]
+    max_reconnect_delay_ms: u64,
+
+    #[structopt(flatten)]
+    framing: FramingOpts,
+
+    #[structopt(flatten)]
+    record: RecordOpts,
+}
+
+/// Runs the tcp command.
+pub fn run(opts: Opts) -> Result<()> {
+    let elf_metadata = opts.common.elf_metadata()?;
+    let mut decoder = opts.framing.serial_decoder(&elf_metadata);
+
+    let backoff = Backoff::new(
+        Duration::from_millis(opts.reconnect_delay_ms),
+        Duration::from_millis(opts.max_reconnect_delay_ms),
+    );
+    let mut source = TcpSource::new(opts.address.as_deref().unwrap(), backoff);
+
+    let mut recorder = opts.record.recorder(&opts.common, &elf_metadata, "tcp")?;
+    decode_stream(
+        &mut source,
+        &mut decoder,
+        &mut recorder,
+        &opts.common.printer(false),
+    )
+}
+
+/// Decodes the rcobs framed logs received from the source until the user requests to exit.
+pub fn decode_stream(
+    source: &mut TcpSource,
+    decoder: &mut SerialDecoder,
+    recorder: &mut Recorder,
+    printer: &Printer,
+) -> Result<()> {
+    let is_app_running = install_ctrlc_handler()?;
+    let mut buffer = [0; 1024];
+    while is_app_running.load(Ordering::Relaxed) {
+        match source.poll(&mut buffer) {
+            TcpEvent::Connected => decoder.resync(),
+            TcpEvent::Data(count) => {
+                let received = SystemTime::now();
+                recorder.record(received, &buffer[..count])?;
+                decoder.feed_and_do_at(&buffer[..count], received, |log| printer.print_log(log))
+            }
+            TcpEvent::Idle | TcpEvent::Disconnected => {}
+        }
+    }
+    log::info!("Closing application. {:?}", decoder.stats());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Write, net::TcpListener};
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(350));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(200));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(350));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(350));
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_tcp_source_reconnects() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            for chunk in [&b"first"[..], b"second"] {
+                let (mut stream, _) = listener.accept().unwrap();
+                stream.write_all(chunk).unwrap();
+            }
+        });
+
+        let backoff = Backoff::new(Duration::from_millis(10), Duration::from_millis(10));
+        let mut source = TcpSource::new(&address, backoff);
+        let mut buffer = [0; 64];
+        let mut events = vec![];
+        let mut received = vec![];
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while events.len() < 6 && Instant::now() < deadline {
+            match source.poll(&mut buffer) {
+                TcpEvent::Idle => {}
+                TcpEvent::Data(count) => {
+                    received.extend_from_slice(&buffer[..count]);
+                    if !matches!(events.last(), Some(TcpEvent::Data(_))) {
+                        events.push(TcpEvent::Data(0));
+                    }
+                }
+                event => events.push(event),
+            }
+        }
+        server.join().unwrap();
+
+        assert_eq!(
+            events,
+            [
+                TcpEvent::Connected,
+                TcpEvent::Data(0),
+                TcpEvent::Disconnected,
+                TcpEvent::Connected,
+                TcpEvent::Data(0),
+                TcpEvent::Disconnected,
+            ]
+        );
+        assert_eq!(received, b"firstsecond");
+        assert!(!source.is_connected());
+    }
+}
@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use postform_decoder::{Decoder, ElfMetadata};
+use std::sync::OnceLock;
+
+static ELF_METADATA: OnceLock<ElfMetadata> = OnceLock::new();
+
+fuzz_target!(|data: &[u8]| {
+    let elf_metadata = ELF_METADATA.get_or_init(postform_decoder_fuzz::elf_metadata);
+    let mut decoder = Decoder::new(elf_metadata);
+    let _ = decoder.decode(data);
+});
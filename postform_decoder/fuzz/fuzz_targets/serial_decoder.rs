@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use postform_decoder::{ElfMetadata, SerialDecoder};
+use std::sync::OnceLock;
+
+static ELF_METADATA: OnceLock<ElfMetadata> = OnceLock::new();
+
+fuzz_target!(|data: &[u8]| {
+    let elf_metadata = ELF_METADATA.get_or_init(postform_decoder_fuzz::elf_metadata);
+    let mut decoder = SerialDecoder::new(elf_metadata);
+    // Feed the data in uneven chunks to exercise frames split across reads
+    for chunk in data.chunks(7) {
+        decoder.feed_and_do(chunk, |_| {});
+    }
+});
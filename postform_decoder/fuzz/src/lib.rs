@@ -0,0 +1,36 @@
+//! Shared helpers for the postform_decoder fuzz targets.
+
+use postform_decoder::{ElfMetadata, POSTFORM_VERSION};
+
+/// Interned strings of the fuzzed firmware, covering every supported conversion.
+const INTERNED_STRINGS: &[&str] = &[
+    "fuzz.cpp@1@Plain message",
+    "fuzz.cpp@2@%d %i %u %o %x %X %p %c",
+    "fuzz.cpp@3@%s and %k",
+    "fuzz.cpp@4@%f %F %e %E %g %G %a %A",
+    "fuzz.cpp@5@%-+ #08.3hhd %lld %zu %5.2s %%",
+    "interned string",
+];
+
+/// Builds the metadata of a firmware with a few log sites, as a dictionary would describe it.
+pub fn elf_metadata() -> ElfMetadata {
+    let mut strings = vec![];
+    for string in INTERNED_STRINGS {
+        strings.extend_from_slice(string.as_bytes());
+        strings.push(0);
+    }
+
+    let mut dictionary = b"PFDICT\x01".to_vec();
+    dictionary.extend_from_slice(&(POSTFORM_VERSION.len() as u32).to_le_bytes());
+    dictionary.extend_from_slice(POSTFORM_VERSION.as_bytes());
+    dictionary.extend_from_slice(&1_000f64.to_le_bytes());
+    dictionary.extend_from_slice(&(strings.len() as u32).to_le_bytes());
+    dictionary.extend_from_slice(&strings);
+    // A single debug section spanning all the log sites
+    dictionary.extend_from_slice(&1u32.to_le_bytes());
+    dictionary.push(0);
+    dictionary.extend_from_slice(&0u64.to_le_bytes());
+    dictionary.extend_from_slice(&(strings.len() as u64).to_le_bytes());
+
+    ElfMetadata::from_dictionary(&dictionary[..], false).expect("Invalid fuzzing dictionary")
+}
@@ -0,0 +1,409 @@
+//! Raw captures of the byte stream received from a transport.
+//!
+//! A capture keeps the exact bytes sent by the target, so that they can be decoded again later,
+//! for instance with a fixed ELF file or a newer decoder. The header identifies the firmware that
+//! produced the data and can embed its metadata dictionary, making the capture self-contained.
+//! All integers are little endian:
+//!
+//! | Field            | Encoding                                                                      |
+//! |------------------|-------------------------------------------------------------------------------|
+//! | Magic            | `PFCAP` followed by the capture format version (u8)                           |
+//! | Firmware version | u32 length + UTF-8 bytes, empty if not given                                  |
+//! | ELF hash         | u32 CRC-32 of the ELF file or dictionary used when recording                  |
+//! | Timestamp freq   | f64                                                                           |
+//! | Transport        | u32 length + UTF-8 bytes                                                      |
+//! | Start time       | u64 host time in microseconds since the UNIX epoch                            |
+//! | Dictionary       | u32 length + metadata dictionary, empty if not embedded                       |
+//! | Chunks           | u64 host receive time in microseconds since the UNIX epoch, u32 length, bytes |
+
+use crate::{ElfMetadata, Error};
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::{
+    io::{Read, Write},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const CAPTURE_MAGIC: &[u8; 5] = b"PFCAP";
+const CAPTURE_VERSION: u8 = 1;
+const CHUNK_HEADER_LEN: usize = 12;
+
+/// Longest string or dictionary accepted in a capture header, to avoid huge allocations when
+/// reading corrupted files.
+const MAX_HEADER_FIELD_LEN: u64 = 64 * 1024 * 1024;
+
+fn to_micros(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64
+}
+
+fn from_micros(micros: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_micros(micros)
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>, Error> {
+    let len = reader.read_u32::<LittleEndian>()? as u64;
+    if len > MAX_HEADER_FIELD_LEN {
+        return Err(Error::InvalidCapture);
+    }
+    let mut bytes = vec![];
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(Error::InvalidCapture);
+    }
+    Ok(bytes)
+}
+
+fn read_string<R: Read>(reader: &mut R) -> Result<String, Error> {
+    String::from_utf8(read_bytes(reader)?).map_err(|_| Error::InvalidCapture)
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<(), Error> {
+    let len = u32::try_from(bytes.len()).map_err(|_| Error::InvalidCapture)?;
+    writer.write_u32::<LittleEndian>(len)?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+/// Returns the hash stored in the capture header for the ELF file or dictionary `data`.
+pub fn elf_hash(data: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Header of a capture, describing the firmware and the session that produced it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureHeader {
+    /// Version of the firmware, as given by the user when recording.
+    pub firmware_version: Option<String>,
+    /// Hash of the ELF file or dictionary used when recording, as returned by [`elf_hash`].
+    pub elf_hash: u32,
+    /// Frequency of the timestamps of the logs, in Hz.
+    pub timestamp_freq: f64,
+    /// Name of the transport the data was received from, like "rtt" or "serial".
+    pub transport: String,
+    /// Host time at which the recording started.
+    pub start_time: SystemTime,
+    /// Metadata dictionary of the firmware, if embedded in the capture.
+    pub dictionary: Option<Vec<u8>>,
+}
+
+impl CaptureHeader {
+    /// Creates the header of a capture recorded now from `transport`. `elf_data` is the ELF file
+    /// or dictionary `elf_metadata` was loaded from and `firmware_version` its version, if known.
+    /// The metadata dictionary is embedded if `embed_dictionary` is set.
+    pub fn new(
+        elf_metadata: &ElfMetadata,
+        elf_data: &[u8],
+        transport: &str,
+        firmware_version: Option<&str>,
+        embed_dictionary: bool,
+    ) -> Result<Self, Error> {
+        let dictionary = if embed_dictionary {
+            let mut dictionary = vec![];
+            elf_metadata.write_dictionary(&mut dictionary)?;
+            Some(dictionary)
+        } else {
+            None
+        };
+        Ok(Self {
+            firmware_version: firmware_version.map(str::to_owned),
+            elf_hash: elf_hash(elf_data),
+            timestamp_freq: elf_metadata.timestamp_freq,
+            transport: transport.to_owned(),
+            start_time: SystemTime::now(),
+            dictionary,
+        })
+    }
+
+    /// Loads the metadata dictionary embedded in the capture, if any.
+    pub fn elf_metadata(&self, disable_version_check: bool) -> Result<Option<ElfMetadata>, Error> {
+        self.dictionary
+            .as_ref()
+            .map(|dictionary| ElfMetadata::from_dictionary(&dictionary[..], disable_version_check))
+            .transpose()
+    }
+
+    fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let mut magic = [0u8; CAPTURE_MAGIC.len()];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|_| Error::InvalidCapture)?;
+        if &magic != CAPTURE_MAGIC || reader.read_u8()? != CAPTURE_VERSION {
+            return Err(Error::InvalidCapture);
+        }
+
+        let firmware_version = Some(read_string(reader)?).filter(|version| !version.is_empty());
+        let elf_hash = reader.read_u32::<LittleEndian>()?;
+        let timestamp_freq = reader.read_f64::<LittleEndian>()?;
+        let transport = read_string(reader)?;
+        let start_time = from_micros(reader.read_u64::<LittleEndian>()?);
+        let dictionary = Some(read_bytes(reader)?).filter(|dictionary| !dictionary.is_empty());
+        Ok(Self {
+            firmware_version,
+            elf_hash,
+            timestamp_freq,
+            transport,
+            start_time,
+            dictionary,
+        })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_all(CAPTURE_MAGIC)?;
+        writer.write_u8(CAPTURE_VERSION)?;
+        write_bytes(
+            writer,
+            self.firmware_version
+                .as_deref()
+                .unwrap_or_default()
+                .as_bytes(),
+        )?;
+        writer.write_u32::<LittleEndian>(self.elf_hash)?;
+        writer.write_f64::<LittleEndian>(self.timestamp_freq)?;
+        write_bytes(writer, self.transport.as_bytes())?;
+        writer.write_u64::<LittleEndian>(to_micros(self.start_time))?;
+        write_bytes(writer, self.dictionary.as_deref().unwrap_or_default())?;
+        Ok(())
+    }
+}
+
+/// A chunk of data received from a transport.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureChunk {
+    /// Host time at which the chunk was received.
+    pub timestamp: SystemTime,
+    /// Raw bytes of the chunk.
+    pub data: Vec<u8>,
+}
+
+/// Writes the chunks of data received from a transport to a capture, each of them with the host
+/// time at which it was received.
+///
+/// Every chunk is handed to the underlying writer with a single `write_all` call, so a capture
+/// written to an unbuffered file stays consistent up to the last chunk if the application is
+/// interrupted.
+pub struct CaptureWriter<W: Write> {
+    writer: W,
+    record: Vec<u8>,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    /// Creates a new CaptureWriter, writing the capture header to `writer`.
+    pub fn new(mut writer: W, header: &CaptureHeader) -> Result<Self, Error> {
+        let mut record = vec![];
+        header.write(&mut record)?;
+        writer.write_all(&record)?;
+        Ok(Self { writer, record })
+    }
+
+    /// Appends a chunk of data received at `timestamp` to the capture.
+    pub fn write_chunk(&mut self, timestamp: SystemTime, data: &[u8]) -> Result<(), Error> {
+        let len = u32::try_from(data.len()).map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "capture chunk too long")
+        })?;
+
+        self.record.clear();
+        self.record
+            .write_u64::<LittleEndian>(to_micros(timestamp))?;
+        self.record.write_u32::<LittleEndian>(len)?;
+        self.record.extend_from_slice(data);
+        self.writer.write_all(&self.record)?;
+        Ok(())
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Unwraps this CaptureWriter, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads the chunks of a capture previously written by a [`CaptureWriter`]. The reader can be
+/// used as an iterator over the chunks.
+///
+/// ```
+/// use postform_decoder::{print_log, CaptureReader, SerialDecoder};
+/// use std::{fs::File, io::BufReader, path::Path};
+/// fn postform_example(capture: &Path) {
+///     let file = BufReader::new(File::open(capture).unwrap());
+///     let reader = CaptureReader::new(file).unwrap();
+///     let elf_metadata = reader.header().elf_metadata(false).unwrap().unwrap();
+///     let mut decoder = SerialDecoder::new(&elf_metadata);
+///     for chunk in reader {
+///         decoder.feed_and_do(&chunk.unwrap().data, |log| match log {
+///             Ok(log) => print_log(&log),
+///             Err(error) => eprintln!("{}", error),
+///         });
+///     }
+/// }
+/// ```
+pub struct CaptureReader<R: Read> {
+    reader: R,
+    header: CaptureHeader,
+    finished: bool,
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Creates a new CaptureReader, reading the capture header from `reader`.
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let header = CaptureHeader::read(&mut reader)?;
+        Ok(Self {
+            reader,
+            header,
+            finished: false,
+        })
+    }
+
+    /// Returns the header of the capture.
+    pub fn header(&self) -> &CaptureHeader {
+        &self.header
+    }
+
+    /// Reads up to `len` bytes, less only if the end of the stream is reached.
+    fn read_up_to(&mut self, len: usize, buffer: &mut Vec<u8>) -> Result<(), Error> {
+        (&mut self.reader).take(len as u64).read_to_end(buffer)?;
+        Ok(())
+    }
+
+    fn read_chunk(&mut self) -> Result<Option<CaptureChunk>, Error> {
+        let mut header = vec![];
+        self.read_up_to(CHUNK_HEADER_LEN, &mut header)?;
+        if header.is_empty() {
+            return Ok(None);
+        }
+        if header.len() < CHUNK_HEADER_LEN {
+            return Err(Error::TruncatedRecord(header.len()));
+        }
+
+        let timestamp = from_micros(LittleEndian::read_u64(&header));
+        let len = LittleEndian::read_u32(&header[8..]) as usize;
+        let mut data = vec![];
+        self.read_up_to(len, &mut data)?;
+        if data.len() < len {
+            return Err(Error::TruncatedRecord(CHUNK_HEADER_LEN + data.len()));
+        }
+        Ok(Some(CaptureChunk { timestamp, data }))
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = Result<CaptureChunk, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let chunk = self.read_chunk().transpose();
+        if !matches!(chunk, Some(Ok(_))) {
+            self.finished = true;
+        }
+        chunk
+    }
+}
+
+/// Returns true if `data` starts like a capture.
+pub fn is_capture(data: &[u8]) -> bool {
+    data.starts_with(CAPTURE_MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_header(dictionary: Option<Vec<u8>>) -> CaptureHeader {
+        CaptureHeader {
+            firmware_version: Some("1.2.3".to_owned()),
+            elf_hash: 0xdeadbeef,
+            timestamp_freq: 1000.0,
+            transport: "rtt".to_owned(),
+            start_time: from_micros(0x0102),
+            dictionary,
+        }
+    }
+
+    #[test]
+    fn test_capture_writer() {
+        let mut writer = CaptureWriter::new(vec![], &create_header(None)).unwrap();
+        writer
+            .write_chunk(from_micros(0x0304), &[0xaa, 0x00])
+            .unwrap();
+        writer.write_chunk(UNIX_EPOCH, &[]).unwrap();
+
+        let mut expected = b"PFCAP\x01".to_vec();
+        expected.extend_from_slice(b"\x05\x00\x00\x001.2.3");
+        expected.extend_from_slice(&[0xef, 0xbe, 0xad, 0xde]);
+        expected.extend_from_slice(&1000f64.to_le_bytes());
+        expected.extend_from_slice(b"\x03\x00\x00\x00rtt");
+        expected.extend_from_slice(&[0x02, 0x01, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[0; 4]);
+        expected.extend_from_slice(&[0x04, 0x03, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[0x02, 0, 0, 0, 0xaa, 0x00]);
+        expected.extend_from_slice(&[0; 12]);
+        assert_eq!(writer.into_inner(), expected);
+    }
+
+    #[test]
+    fn test_capture_round_trip() {
+        let header = create_header(Some(vec![0x01, 0x02]));
+        let chunks = [
+            CaptureChunk {
+                timestamp: from_micros(1_650_000_000_123_456),
+                data: vec![0x01, 0x02, 0x00],
+            },
+            CaptureChunk {
+                timestamp: from_micros(1_650_000_000_223_456),
+                data: vec![0x03; 300],
+            },
+        ];
+        let mut writer = CaptureWriter::new(vec![], &header).unwrap();
+        for chunk in &chunks {
+            writer.write_chunk(chunk.timestamp, &chunk.data).unwrap();
+        }
+        let mut capture = writer.into_inner();
+        assert!(is_capture(&capture));
+
+        let reader = CaptureReader::new(&capture[..]).unwrap();
+        assert_eq!(reader.header(), &header);
+        let read: Vec<_> = reader.map(Result::unwrap).collect();
+        assert_eq!(read, chunks);
+
+        // Chunk cut short by the end of the stream
+        capture.truncate(capture.len() - 10);
+        let mut reader = CaptureReader::new(&capture[..]).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap(), chunks[0]);
+        assert!(matches!(
+            reader.next(),
+            Some(Err(Error::TruncatedRecord(302)))
+        ));
+        assert!(reader.next().is_none());
+
+        // Capture without a firmware version
+        let header = CaptureHeader {
+            firmware_version: None,
+            ..header
+        };
+        let capture = CaptureWriter::new(vec![], &header).unwrap().into_inner();
+        assert_eq!(CaptureReader::new(&capture[..]).unwrap().header(), &header);
+
+        assert!(matches!(
+            CaptureReader::new(&b"PFDICT\x01"[..]),
+            Err(Error::InvalidCapture)
+        ));
+        assert!(matches!(
+            CaptureReader::new(&b"PF"[..]),
+            Err(Error::InvalidCapture)
+        ));
+        // Header field longer than the rest of the file
+        assert!(matches!(
+            CaptureReader::new(&b"PFCAP\x01\xff\x00\x00\x001.2.3"[..]),
+            Err(Error::InvalidCapture)
+        ));
+    }
+}
@@ -0,0 +1,111 @@
+//! Standalone metadata dictionary.
+//!
+//! A dictionary holds everything the decoder needs from the firmware ELF file, so that logs can
+//! be decoded without shipping the ELF file itself. All integers are little endian:
+//!
+//! | Field              | Encoding                                               |
+//! |--------------------|--------------------------------------------------------|
+//! | Magic              | `PFDICT` followed by the dictionary format version (u8) |
+//! | Postform version   | u32 length + UTF-8 bytes                               |
+//! | Timestamp freq     | f64                                                    |
+//! | Interned strings   | u32 length + bytes                                     |
+//! | Log sections       | u32 count + (u8 level, u64 start, u64 end) per section |
+
+use crate::{check_postform_version, ElfMetadata, Error, LogLevel, LogSection};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{Read, Write};
+
+const DICTIONARY_MAGIC: &[u8; 6] = b"PFDICT";
+const DICTIONARY_VERSION: u8 = 1;
+
+fn read_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>, Error> {
+    let len = reader.read_u32::<LittleEndian>()? as u64;
+    let mut bytes = vec![];
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(Error::InvalidDictionary);
+    }
+    Ok(bytes)
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<(), Error> {
+    let len = u32::try_from(bytes.len()).map_err(|_| Error::InvalidDictionary)?;
+    writer.write_u32::<LittleEndian>(len)?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+fn level_from_u8(level: u8) -> Result<LogLevel, Error> {
+    [
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Warning,
+        LogLevel::Error,
+    ]
+    .get(level as usize)
+    .copied()
+    .ok_or(Error::InvalidDictionary)
+}
+
+impl ElfMetadata {
+    /// Returns true if `data` starts like a metadata dictionary.
+    pub fn is_dictionary(data: &[u8]) -> bool {
+        data.starts_with(DICTIONARY_MAGIC)
+    }
+
+    /// Attempts to instantiate the ElfMetadata struct from a metadata dictionary previously
+    /// written with [`write_dictionary`](ElfMetadata::write_dictionary).
+    pub fn from_dictionary<R: Read>(
+        mut reader: R,
+        disable_version_check: bool,
+    ) -> Result<Self, Error> {
+        let mut magic = [0u8; DICTIONARY_MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != DICTIONARY_MAGIC || reader.read_u8()? != DICTIONARY_VERSION {
+            return Err(Error::InvalidDictionary);
+        }
+
+        let postform_version =
+            String::from_utf8(read_bytes(&mut reader)?).map_err(|_| Error::InvalidDictionary)?;
+        check_postform_version(&postform_version, disable_version_check)?;
+
+        let timestamp_freq = reader.read_f64::<LittleEndian>()?;
+        let strings = read_bytes(&mut reader)?;
+
+        let section_count = reader.read_u32::<LittleEndian>()?;
+        let mut log_sections = vec![];
+        for _ in 0..section_count {
+            let level = level_from_u8(reader.read_u8()?)?;
+            let start = reader.read_u64::<LittleEndian>()? as usize;
+            let end = reader.read_u64::<LittleEndian>()? as usize;
+            log_sections.push(LogSection { level, start, end });
+        }
+
+        Ok(Self {
+            postform_version,
+            timestamp_freq,
+            strings,
+            log_sections,
+        })
+    }
+
+    /// Writes all the log metadata into `writer` as a standalone dictionary, which can be loaded
+    /// back with [`from_dictionary`](ElfMetadata::from_dictionary).
+    pub fn write_dictionary<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writer.write_all(DICTIONARY_MAGIC)?;
+        writer.write_u8(DICTIONARY_VERSION)?;
+        write_bytes(&mut writer, self.postform_version.as_bytes())?;
+        writer.write_f64::<LittleEndian>(self.timestamp_freq)?;
+        write_bytes(&mut writer, &self.strings)?;
+
+        let section_count =
+            u32::try_from(self.log_sections.len()).map_err(|_| Error::InvalidDictionary)?;
+        writer.write_u32::<LittleEndian>(section_count)?;
+        for section in &self.log_sections {
+            writer.write_u8(section.level as u8)?;
+            writer.write_u64::<LittleEndian>(section.start as u64)?;
+            writer.write_u64::<LittleEndian>(section.end as u64)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,273 @@
+//! Host-side filtering of decoded logs.
+
+use crate::{Error, Log, LogLevel};
+use regex::Regex;
+use std::ops::RangeInclusive;
+
+/// Glob matched against the file name of a log.
+///
+/// `*` matches any sequence of characters except `/`, `**` matches any sequence of characters
+/// including `/` and `?` matches a single character except `/`. The glob is matched against the
+/// end of the file name, starting at a path component, so `spi.cpp` and `drivers/*.cpp` both
+/// match `../src/drivers/spi.cpp`.
+#[derive(Clone, Debug)]
+pub struct FileGlob {
+    glob: String,
+    regex: Regex,
+}
+
+impl FileGlob {
+    /// Returns true if the file name matches the glob.
+    pub fn matches(&self, file_name: &str) -> bool {
+        self.regex.is_match(file_name)
+    }
+
+    /// Returns the glob as given by the user.
+    pub fn as_str(&self) -> &str {
+        &self.glob
+    }
+}
+
+impl std::str::FromStr for FileGlob {
+    type Err = Error;
+
+    fn from_str(glob: &str) -> Result<Self, Self::Err> {
+        let mut pattern = String::from("(?:^|/)");
+        let mut chars = glob.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.next_if_eq(&'*').is_some() => pattern.push_str(".*"),
+                '*' => pattern.push_str("[^/]*"),
+                '?' => pattern.push_str("[^/]"),
+                c => pattern.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+            }
+        }
+        pattern.push('$');
+        let regex = Regex::new(&pattern).map_err(|_| Error::InvalidFilter(glob.to_owned()))?;
+        Ok(Self {
+            glob: glob.to_owned(),
+            regex,
+        })
+    }
+}
+
+/// Inclusive range of line numbers, written as `10-20`, `10-`, `-20` or just `15`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineRange(RangeInclusive<u32>);
+
+impl LineRange {
+    /// Returns true if the line is within the range.
+    pub fn contains(&self, line: u32) -> bool {
+        self.0.contains(&line)
+    }
+}
+
+impl std::str::FromStr for LineRange {
+    type Err = Error;
+
+    fn from_str(range: &str) -> Result<Self, Self::Err> {
+        let error = || Error::InvalidFilter(range.to_owned());
+        let parse_bound = |bound: &str, default: u32| match bound.trim() {
+            "" => Ok(default),
+            bound => bound.parse::<u32>().map_err(|_| error()),
+        };
+
+        let (start, end) = match range.split_once('-') {
+            Some((start, end)) => (parse_bound(start, u32::MIN)?, parse_bound(end, u32::MAX)?),
+            None => {
+                let line = range.trim().parse::<u32>().map_err(|_| error())?;
+                (line, line)
+            }
+        };
+        if start > end {
+            return Err(error());
+        }
+        Ok(Self(start..=end))
+    }
+}
+
+/// Selects which logs are kept. A log passes the filter if it matches all the configured
+/// predicates. The repeatable predicates (included files, messages and line ranges) match if any
+/// of their entries match, while a log is dropped if its file matches any of the excluded files.
+///
+/// ```
+/// use postform_decoder::{LogFilter, LogLevel};
+/// let filter = LogFilter::new()
+///     .min_level(LogLevel::Warning)
+///     .include_file("drivers/spi.cpp".parse().unwrap())
+///     .message("timeout|overrun".parse().unwrap());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct LogFilter {
+    min_level: Option<LogLevel>,
+    include_files: Vec<FileGlob>,
+    exclude_files: Vec<FileGlob>,
+    messages: Vec<Regex>,
+    line_ranges: Vec<LineRange>,
+}
+
+impl LogFilter {
+    /// Creates a filter that keeps every log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drops the logs with a level lower than `level`. Logs with an unknown level are dropped as
+    /// well, since their severity can't be compared.
+    pub fn min_level(mut self, level: LogLevel) -> Self {
+        self.min_level = Some(level);
+        self
+    }
+
+    /// Keeps only the logs from files matching this glob or any other included one.
+    pub fn include_file(mut self, glob: FileGlob) -> Self {
+        self.include_files.push(glob);
+        self
+    }
+
+    /// Drops the logs from files matching this glob.
+    pub fn exclude_file(mut self, glob: FileGlob) -> Self {
+        self.exclude_files.push(glob);
+        self
+    }
+
+    /// Keeps only the logs whose rendered message matches this regex or any other given one.
+    pub fn message(mut self, regex: Regex) -> Self {
+        self.messages.push(regex);
+        self
+    }
+
+    /// Keeps only the logs whose line number is within this range or any other given one.
+    pub fn line_range(mut self, range: LineRange) -> Self {
+        self.line_ranges.push(range);
+        self
+    }
+
+    /// Returns true if the log passes the filter.
+    pub fn matches(&self, log: &Log) -> bool {
+        fn any_or_empty<T>(entries: &[T], predicate: impl Fn(&T) -> bool) -> bool {
+            entries.is_empty() || entries.iter().any(predicate)
+        }
+
+        self.min_level
+            .into_iter()
+            .all(|level| log.level != LogLevel::Unknown && log.level >= level)
+            && any_or_empty(&self.include_files, |glob| glob.matches(&log.file_name))
+            && !self
+                .exclude_files
+                .iter()
+                .any(|glob| glob.matches(&log.file_name))
+            && any_or_empty(&self.messages, |regex| regex.is_match(&log.message))
+            && any_or_empty(&self.line_ranges, |range| range.contains(log.line_number))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_log(level: LogLevel, file_name: &str, line_number: u32, message: &str) -> Log {
+        Log {
+            timestamp: 0.0,
+            host_timestamp: None,
+            channel: None,
+            core: None,
+            level,
+            message: message.to_owned(),
+            file_name: file_name.into(),
+            line_number,
+            format: message.into(),
+            args: vec![],
+        }
+    }
+
+    #[test]
+    fn test_file_glob() {
+        let matches =
+            |glob: &str, file_name: &str| glob.parse::<FileGlob>().unwrap().matches(file_name);
+        assert!(matches("spi.cpp", "../src/drivers/spi.cpp"));
+        assert!(matches("spi.cpp", "spi.cpp"));
+        assert!(!matches("spi.cpp", "../src/drivers/qspi.cpp"));
+        assert!(matches("*.cpp", "../src/drivers/spi.cpp"));
+        assert!(!matches("*.cpp", "../src/drivers/spi.hpp"));
+        assert!(matches("drivers/*.cpp", "../src/drivers/spi.cpp"));
+        assert!(!matches("drivers/*.cpp", "../src/drivers/bus/spi.cpp"));
+        assert!(matches("drivers/**.cpp", "../src/drivers/bus/spi.cpp"));
+        assert!(matches("s?i.cpp", "src/spi.cpp"));
+        assert!(!matches("s?i.cpp", "src/s/i.cpp"));
+        assert!(matches("main(1).cpp", "main(1).cpp"));
+    }
+
+    #[test]
+    fn test_line_range() {
+        let range = |range: &str| range.parse::<LineRange>();
+        assert_eq!(range("10-20").unwrap(), LineRange(10..=20));
+        assert_eq!(range("15").unwrap(), LineRange(15..=15));
+        assert_eq!(range("10-").unwrap(), LineRange(10..=u32::MAX));
+        assert_eq!(range("-20").unwrap(), LineRange(0..=20));
+        assert!(range("20-10").is_err());
+        assert!(range("a-b").is_err());
+        assert!(range("").is_err());
+    }
+
+    #[test]
+    fn test_log_filter_unknown_level() {
+        let log = create_log(LogLevel::Unknown, "src/main.cpp", 1, "corrupted");
+        assert!(LogFilter::new().matches(&log));
+        assert!(!LogFilter::new().min_level(LogLevel::Debug).matches(&log));
+        assert!(!LogFilter::new().min_level(LogLevel::Error).matches(&log));
+    }
+
+    #[test]
+    fn test_log_filter() {
+        let logs = [
+            create_log(LogLevel::Debug, "src/drivers/spi.cpp", 10, "transfer done"),
+            create_log(LogLevel::Warning, "src/drivers/spi.cpp", 42, "timeout"),
+            create_log(LogLevel::Error, "src/drivers/i2c.cpp", 42, "nack"),
+            create_log(LogLevel::Warning, "src/main.cpp", 100, "low battery"),
+        ];
+        let kept = |filter: &LogFilter| -> Vec<usize> {
+            (0..logs.len())
+                .filter(|index| filter.matches(&logs[*index]))
+                .collect()
+        };
+
+        assert_eq!(kept(&LogFilter::new()), [0, 1, 2, 3]);
+        assert_eq!(
+            kept(&LogFilter::new().min_level(LogLevel::Warning)),
+            [1, 2, 3]
+        );
+        assert_eq!(
+            kept(
+                &LogFilter::new()
+                    .min_level(LogLevel::Warning)
+                    .include_file("spi.cpp".parse().unwrap())
+            ),
+            [1]
+        );
+        assert_eq!(
+            kept(
+                &LogFilter::new()
+                    .include_file("drivers/*".parse().unwrap())
+                    .exclude_file("i2c.cpp".parse().unwrap())
+            ),
+            [0, 1]
+        );
+        assert_eq!(
+            kept(
+                &LogFilter::new()
+                    .message("timeout".parse().unwrap())
+                    .message("^low".parse().unwrap())
+            ),
+            [1, 3]
+        );
+        assert_eq!(
+            kept(
+                &LogFilter::new()
+                    .line_range("1-10".parse().unwrap())
+                    .line_range("100-".parse().unwrap())
+            ),
+            [0, 3]
+        );
+    }
+}
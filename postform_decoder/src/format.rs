@@ -0,0 +1,460 @@
+//! Parsing and rendering of printf-like conversion specifications.
+//!
+//! The grammar follows the C standard:
+//! `%[flags][width][.precision][length]conversion`. Rendering mimics the output of glibc's
+//! printf, so that format strings shared with printf-based builds produce the same text.
+
+use crate::{ArgValue, Error};
+
+/// Flags of a conversion specification.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Flags {
+    /// `-`: Left-justify within the field width.
+    pub left_align: bool,
+    /// `+`: Always print the sign of signed conversions.
+    pub plus_sign: bool,
+    /// ` `: Prefix positive signed conversions with a space.
+    pub space_sign: bool,
+    /// `#`: Alternate form (`0` prefix for octal, `0x` for hex).
+    pub alternate: bool,
+    /// `0`: Pad numeric conversions with leading zeros.
+    pub zero_pad: bool,
+}
+
+/// Length modifier of a conversion specification.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Length {
+    /// No length modifier (`int`).
+    Default,
+    /// `hh` (`char`).
+    Char,
+    /// `h` (`short`).
+    Short,
+    /// `l` (`long`).
+    Long,
+    /// `ll` (`long long`).
+    LongLong,
+    /// `z` (`size_t`).
+    Size,
+    /// `j` (`intmax_t`).
+    IntMax,
+    /// `t` (`ptrdiff_t`).
+    PtrDiff,
+    /// `L` (`long double`).
+    LongDouble,
+}
+
+/// A parsed conversion specification, like `%-08.3lx`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct FormatSpec {
+    pub flags: Flags,
+    pub width: Option<usize>,
+    pub precision: Option<usize>,
+    pub length: Length,
+    pub conversion: char,
+}
+
+fn parse_number(spec: &[u8], pos: &mut usize) -> Option<usize> {
+    let start = *pos;
+    while *pos < spec.len() && spec[*pos].is_ascii_digit() {
+        *pos += 1;
+    }
+    std::str::from_utf8(&spec[start..*pos]).ok()?.parse().ok()
+}
+
+impl FormatSpec {
+    /// Parses a conversion specification. `spec` must point right after the `%` sign.
+    /// Returns the specification along with the number of bytes it takes in `spec`.
+    pub(crate) fn parse(spec: &str) -> Result<(Self, usize), Error> {
+        let bytes = spec.as_bytes();
+        let mut pos = 0;
+
+        let mut flags = Flags::default();
+        while pos < bytes.len() {
+            match bytes[pos] {
+                b'-' => flags.left_align = true,
+                b'+' => flags.plus_sign = true,
+                b' ' => flags.space_sign = true,
+                b'#' => flags.alternate = true,
+                b'0' => flags.zero_pad = true,
+                _ => break,
+            }
+            pos += 1;
+        }
+
+        let width = parse_number(bytes, &mut pos);
+        let precision = if bytes.get(pos) == Some(&b'.') {
+            pos += 1;
+            // A lone '.' means a precision of zero
+            Some(parse_number(bytes, &mut pos).unwrap_or(0))
+        } else {
+            None
+        };
+
+        let remaining = &bytes[pos..];
+        let (length, length_len) = if remaining.starts_with(b"hh") {
+            (Length::Char, 2)
+        } else if remaining.starts_with(b"ll") {
+            (Length::LongLong, 2)
+        } else {
+            match remaining.first() {
+                Some(b'h') => (Length::Short, 1),
+                Some(b'l') => (Length::Long, 1),
+                Some(b'z') => (Length::Size, 1),
+                Some(b'j') => (Length::IntMax, 1),
+                Some(b't') => (Length::PtrDiff, 1),
+                Some(b'L') => (Length::LongDouble, 1),
+                _ => (Length::Default, 0),
+            }
+        };
+        pos += length_len;
+
+        let conversion = spec[pos..]
+            .chars()
+            .next()
+            .ok_or(Error::InvalidFormatSpecifier('%'))?;
+        pos += conversion.len_utf8();
+
+        Ok((
+            FormatSpec {
+                flags,
+                width,
+                precision,
+                length,
+                conversion,
+            },
+            pos,
+        ))
+    }
+
+    /// Renders a decoded argument according to this specification.
+    pub(crate) fn render(&self, out_str: &mut String, value: &ArgValue) {
+        match value {
+            ArgValue::Signed(value) => self.render_signed(out_str, *value),
+            ArgValue::Unsigned(value) => self.render_unsigned(out_str, *value),
+            ArgValue::Float(value) => self.render_float(out_str, *value),
+            ArgValue::Char(value) => self.render_char(out_str, *value),
+            ArgValue::String(value) | ArgValue::InternedString(value) => {
+                self.render_str(out_str, value)
+            }
+            ArgValue::Pointer(value) => self.render_pointer(out_str, *value),
+        }
+    }
+
+    /// Truncates a signed value to the size given by the length modifier.
+    pub(crate) fn truncate_signed(&self, value: i64) -> i64 {
+        match self.length {
+            Length::Char => value as i8 as i64,
+            Length::Short => value as i16 as i64,
+            Length::Default => value as i32 as i64,
+            _ => value,
+        }
+    }
+
+    /// Truncates an unsigned value to the size given by the length modifier.
+    pub(crate) fn truncate_unsigned(&self, value: u64) -> u64 {
+        match self.length {
+            Length::Char => value as u8 as u64,
+            Length::Short => value as u16 as u64,
+            Length::Default => value as u32 as u64,
+            _ => value,
+        }
+    }
+
+    /// Pads `body` with spaces up to the field width.
+    fn pad(&self, out_str: &mut String, body: &str) {
+        let fill = self.width.unwrap_or(0).saturating_sub(body.chars().count());
+        if !self.flags.left_align {
+            out_str.push_str(&" ".repeat(fill));
+        }
+        out_str.push_str(body);
+        if self.flags.left_align {
+            out_str.push_str(&" ".repeat(fill));
+        }
+    }
+
+    /// Number of digits required to fill the field width with zeros after the prefix, if
+    /// zero padding was requested.
+    fn zero_pad_len(&self, prefix: &str) -> usize {
+        if self.flags.zero_pad && !self.flags.left_align {
+            self.width.unwrap_or(0).saturating_sub(prefix.len())
+        } else {
+            0
+        }
+    }
+
+    /// Renders a number given its prefix (sign, base prefix) and its digits, left-padding the
+    /// digits with zeros up to `min_digits`.
+    fn render_digits(&self, out_str: &mut String, prefix: &str, digits: &str, min_digits: usize) {
+        let mut body = String::from(prefix);
+        body.push_str(&"0".repeat(min_digits.saturating_sub(digits.len())));
+        body.push_str(digits);
+        self.pad(out_str, &body);
+    }
+
+    /// Renders an integer given its prefix (sign, base prefix) and its digits, applying the
+    /// precision and the zero padding.
+    fn render_number(&self, out_str: &mut String, prefix: &str, digits: &str) {
+        let min_digits = self.precision.unwrap_or_else(|| self.zero_pad_len(prefix));
+        self.render_digits(out_str, prefix, digits, min_digits);
+    }
+
+    fn sign_prefix(&self, negative: bool) -> &'static str {
+        if negative {
+            "-"
+        } else if self.flags.plus_sign {
+            "+"
+        } else if self.flags.space_sign {
+            " "
+        } else {
+            ""
+        }
+    }
+
+    /// Renders a signed integer conversion (`%d`, `%i`).
+    fn render_signed(&self, out_str: &mut String, value: i64) {
+        let digits = if self.precision == Some(0) && value == 0 {
+            String::new()
+        } else {
+            value.unsigned_abs().to_string()
+        };
+        self.render_number(out_str, self.sign_prefix(value < 0), &digits);
+    }
+
+    /// Renders an unsigned integer conversion (`%u`, `%o`, `%x`, `%X`).
+    fn render_unsigned(&self, out_str: &mut String, value: u64) {
+        let mut digits = if self.precision == Some(0) && value == 0 {
+            String::new()
+        } else {
+            match self.conversion {
+                'o' => format!("{:o}", value),
+                'x' => format!("{:x}", value),
+                'X' => format!("{:X}", value),
+                _ => value.to_string(),
+            }
+        };
+
+        let mut prefix = "";
+        if self.flags.alternate {
+            match self.conversion {
+                // The alternate form increases the precision to force a leading zero
+                'o' if !digits.starts_with('0') => digits.insert(0, '0'),
+                'x' if value != 0 => prefix = "0x",
+                'X' if value != 0 => prefix = "0X",
+                _ => {}
+            }
+        }
+        self.render_number(out_str, prefix, &digits);
+    }
+
+    /// Renders a pointer conversion (`%p`).
+    fn render_pointer(&self, out_str: &mut String, value: u64) {
+        if value == 0 {
+            self.pad(out_str, "(nil)");
+            return;
+        }
+
+        let prefix = format!("{}0x", self.sign_prefix(false));
+        self.render_number(out_str, &prefix, &format!("{:x}", value));
+    }
+
+    /// Renders a string conversion (`%s`, `%k`).
+    fn render_str(&self, out_str: &mut String, value: &str) {
+        match self.precision {
+            Some(precision) => {
+                // The precision limits the number of bytes that are printed.
+                let mut end = precision.min(value.len());
+                while !value.is_char_boundary(end) {
+                    end -= 1;
+                }
+                self.pad(out_str, &value[..end]);
+            }
+            None => self.pad(out_str, value),
+        }
+    }
+
+    /// Renders a character conversion (`%c`).
+    fn render_char(&self, out_str: &mut String, value: char) {
+        let mut buffer = [0u8; 4];
+        self.pad(out_str, value.encode_utf8(&mut buffer));
+    }
+
+    /// Renders a floating point conversion (`%f`, `%e`, `%g`, `%a` and their uppercase
+    /// variants).
+    fn render_float(&self, out_str: &mut String, value: f64) {
+        let uppercase = self.conversion.is_ascii_uppercase();
+        let sign = self.sign_prefix(value.is_sign_negative());
+
+        if !value.is_finite() {
+            // Infinity and NaN are never zero padded
+            let body = if value.is_nan() { "nan" } else { "inf" };
+            let body = if uppercase {
+                body.to_ascii_uppercase()
+            } else {
+                body.to_owned()
+            };
+            self.pad(out_str, &format!("{}{}", sign, body));
+            return;
+        }
+
+        let value = value.abs();
+        let (prefix, digits) = match self.conversion.to_ascii_lowercase() {
+            'f' => (
+                sign.to_owned(),
+                self.fixed_digits(value, self.float_precision()),
+            ),
+            'e' => (
+                sign.to_owned(),
+                self.exponent_digits(value, self.float_precision()),
+            ),
+            'g' => (sign.to_owned(), self.general_digits(value)),
+            _ => (
+                format!("{}0{}", sign, if uppercase { 'X' } else { 'x' }),
+                self.hex_digits(value),
+            ),
+        };
+
+        let digits = if uppercase {
+            digits.to_ascii_uppercase()
+        } else {
+            digits
+        };
+        // Unlike integers, the precision of a float does not disable zero padding
+        self.render_digits(out_str, &prefix, &digits, self.zero_pad_len(&prefix));
+    }
+
+    fn float_precision(&self) -> usize {
+        self.precision.unwrap_or(6)
+    }
+
+    /// Digits of a positive float in the style of `%f`.
+    fn fixed_digits(&self, value: f64, precision: usize) -> String {
+        let mut digits = format!("{:.*}", precision, value);
+        if precision == 0 && self.flags.alternate {
+            digits.push('.');
+        }
+        digits
+    }
+
+    /// Digits of a positive float in the style of `%e`.
+    fn exponent_digits(&self, value: f64, precision: usize) -> String {
+        let digits = format!("{:.*e}", precision, value);
+        // Rust formats the exponent without sign or leading zeros. C uses at least 2 digits.
+        let (mantissa, exponent) = digits.split_once('e').unwrap_or((&digits, "0"));
+        let exponent: i32 = exponent.parse().unwrap_or(0);
+        format!(
+            "{}{}e{}{:02}",
+            mantissa,
+            if precision == 0 && self.flags.alternate {
+                "."
+            } else {
+                ""
+            },
+            if exponent < 0 { '-' } else { '+' },
+            exponent.unsigned_abs()
+        )
+    }
+
+    /// Digits of a positive float in the style of `%g`.
+    fn general_digits(&self, value: f64) -> String {
+        let precision = match self.float_precision() {
+            0 => 1,
+            precision => precision,
+        };
+
+        // The exponent is obtained after rounding to the requested precision
+        let exponent = if value == 0.0 {
+            0
+        } else {
+            let digits = format!("{:.*e}", precision - 1, value);
+            digits
+                .split_once('e')
+                .and_then(|(_, exponent)| exponent.parse::<i64>().ok())
+                .unwrap_or(0)
+        };
+
+        let mut digits = if exponent >= -4 && exponent < precision as i64 {
+            self.fixed_digits(value, (precision as i64 - 1 - exponent) as usize)
+        } else {
+            self.exponent_digits(value, precision - 1)
+        };
+
+        if !self.flags.alternate {
+            // Remove trailing zeros from the fractional part, along with the decimal point
+            let mantissa_end = digits.find('e').unwrap_or(digits.len());
+            if digits[..mantissa_end].contains('.') {
+                let trimmed_end = digits[..mantissa_end].trim_end_matches('0').len();
+                let trimmed_end = if digits[..trimmed_end].ends_with('.') {
+                    trimmed_end - 1
+                } else {
+                    trimmed_end
+                };
+                digits.replace_range(trimmed_end..mantissa_end, "");
+            }
+        }
+        digits
+    }
+
+    /// Digits of a positive float in the style of `%a`, without the `0x` prefix.
+    fn hex_digits(&self, value: f64) -> String {
+        const MANTISSA_BITS: u32 = 52;
+        const MANTISSA_DIGITS: usize = 13;
+
+        let bits = value.to_bits();
+        let biased_exponent = ((bits >> MANTISSA_BITS) & 0x7ff) as i64;
+        let mut mantissa = bits & ((1 << MANTISSA_BITS) - 1);
+        let (mut leading, exponent) = match (biased_exponent, mantissa) {
+            (0, 0) => (0, 0),
+            // Subnormal numbers
+            (0, _) => (0, -1022),
+            _ => (1, biased_exponent - 1023),
+        };
+
+        let num_digits = match self.precision {
+            Some(precision) if precision < MANTISSA_DIGITS => {
+                // Round to nearest, ties to even
+                let shift = (MANTISSA_DIGITS - precision) as u32 * 4;
+                let remainder = mantissa & ((1 << shift) - 1);
+                let half = 1 << (shift - 1);
+                mantissa >>= shift;
+                let last_digit = if precision == 0 { leading } else { mantissa };
+                if remainder > half || (remainder == half && (last_digit & 1) == 1) {
+                    mantissa += 1;
+                    if mantissa >> (precision * 4) != 0 {
+                        mantissa &= (1 << (precision * 4)) - 1;
+                        leading += 1;
+                    }
+                }
+                precision
+            }
+            Some(_) => MANTISSA_DIGITS,
+            None => {
+                // Print as many digits as required to represent the value exactly
+                let trailing_zero_digits = if mantissa == 0 {
+                    MANTISSA_DIGITS
+                } else {
+                    mantissa.trailing_zeros() as usize / 4
+                };
+                mantissa >>= trailing_zero_digits * 4;
+                MANTISSA_DIGITS - trailing_zero_digits
+            }
+        };
+
+        let mut digits = format!("{}", leading);
+        if num_digits > 0 || self.flags.alternate {
+            digits.push('.');
+        }
+        if num_digits > 0 {
+            digits.push_str(&format!("{:0width$x}", mantissa, width = num_digits));
+        }
+        if let Some(precision) = self.precision {
+            digits.push_str(&"0".repeat(precision.saturating_sub(MANTISSA_DIGITS)));
+        }
+        digits.push_str(&format!(
+            "p{}{}",
+            if exponent < 0 { '-' } else { '+' },
+            exponent.unsigned_abs()
+        ));
+        digits
+    }
+}
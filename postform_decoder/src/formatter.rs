@@ -0,0 +1,637 @@
+//! Rendering of decoded logs.
+//!
+//! The [`LogFormatter`] trait renders a log into any writer. [`DefaultFormatter`] implements the
+//! classic two-line layout, while [`TemplateFormatter`] renders logs following a user-provided
+//! template like `"{t:>10.3} {L:<7} {file}:{line} {msg}"`.
+
+use crate::{Error, Log, LogLevel};
+use std::io::{self, Write};
+
+/// Renders decoded logs. Implementations write the complete log, including the trailing newline.
+pub trait LogFormatter {
+    /// Writes `log` to `writer`.
+    fn format(&self, log: &Log, writer: &mut dyn Write) -> io::Result<()>;
+}
+
+/// Returns the associated color for the log level
+fn color_for_level(level: LogLevel) -> colored::Color {
+    match level {
+        LogLevel::Debug => colored::Color::Green,
+        LogLevel::Info => colored::Color::Yellow,
+        LogLevel::Warning => colored::Color::TrueColor {
+            r: 0xFFu8,
+            g: 0xA5u8,
+            b: 0u8,
+        },
+        LogLevel::Error => colored::Color::Red,
+        LogLevel::Unknown => colored::Color::Red,
+    }
+}
+
+fn level_name(level: LogLevel) -> &'static str {
+    level.into()
+}
+
+/// Selects the timestamps printed along with each log in the text output.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// Target time, in seconds since the target started counting.
+    Target,
+    /// Host wall-clock time at which the log was received, in RFC 3339 format.
+    Host,
+    /// Host wall-clock time followed by the target time.
+    Both,
+}
+
+/// Error returned when parsing an unknown [`TimestampFormat`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid timestamp format \"{0}\"")]
+pub struct ParseTimestampFormatError(String);
+
+impl std::str::FromStr for TimestampFormat {
+    type Err = ParseTimestampFormatError;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "target" => Ok(TimestampFormat::Target),
+            "host" => Ok(TimestampFormat::Host),
+            "both" => Ok(TimestampFormat::Both),
+            _ => Err(ParseTimestampFormatError(format.to_owned())),
+        }
+    }
+}
+
+/// Style applied to a part of a log.
+enum Style {
+    Color(colored::Color),
+    Dimmed,
+}
+
+/// Applies `style` to `text` if colors are enabled. The escape codes are written directly, as
+/// the `colored` crate would skip them when stdout is not a terminal.
+fn colorize(text: &str, color: bool, style: Style) -> String {
+    if !color {
+        return text.to_owned();
+    }
+    let code = match style {
+        Style::Color(color) => color.to_fg_str(),
+        Style::Dimmed => "2".into(),
+    };
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
+
+/// Whether to color the output by default, following the environment and whether stdout is a
+/// terminal.
+fn default_color() -> bool {
+    colored::control::SHOULD_COLORIZE.should_colorize()
+}
+
+/// Formats the host timestamp of the log, or a placeholder of the same width if the log has none.
+fn format_host_timestamp(log: &Log) -> String {
+    match log.host_timestamp {
+        Some(timestamp) => humantime::format_rfc3339_micros(timestamp).to_string(),
+        None => format!("{:<27}", "-"),
+    }
+}
+
+/// Two-line layout with the timestamps, the core if enabled, the channel if known, the colored
+/// level and the message, followed by the file name and line number of the log.
+#[derive(Copy, Clone, Debug)]
+pub struct DefaultFormatter {
+    timestamps: TimestampFormat,
+    color: bool,
+    show_core: bool,
+}
+
+impl DefaultFormatter {
+    /// Creates a new DefaultFormatter that prints the selected timestamps.
+    pub fn new(timestamps: TimestampFormat) -> Self {
+        Self {
+            timestamps,
+            color: default_color(),
+            show_core: false,
+        }
+    }
+
+    /// Prints the core of the logs that have one. Disabled by default, since the core is only
+    /// relevant when decoding the logs of several cores at once.
+    pub fn show_core(mut self, show_core: bool) -> Self {
+        self.show_core = show_core;
+        self
+    }
+
+    /// Enables or disables the colors of the output. By default the output is colored when
+    /// stdout is a terminal, unless disabled through the environment, e.g. with `NO_COLOR`.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+impl Default for DefaultFormatter {
+    fn default() -> Self {
+        Self::new(TimestampFormat::Target)
+    }
+}
+
+impl LogFormatter for DefaultFormatter {
+    fn format(&self, log: &Log, writer: &mut dyn Write) -> io::Result<()> {
+        let timestamp = match self.timestamps {
+            TimestampFormat::Target => format!("{:<12.6}", log.timestamp),
+            TimestampFormat::Host => format_host_timestamp(log),
+            TimestampFormat::Both => {
+                format!("{} {:<12.6}", format_host_timestamp(log), log.timestamp)
+            }
+        };
+        let mut source = String::new();
+        if let (true, Some(core)) = (self.show_core, log.core) {
+            source.push_str(&format!("[core {}] ", core));
+        }
+        if let Some(channel) = &log.channel {
+            source.push_str(&format!("[{}] ", channel));
+        }
+        writeln!(
+            writer,
+            "{timestamp} {source}{level}: {msg}",
+            timestamp = timestamp,
+            source = source,
+            level = colorize(
+                &format!("{:<11}", level_name(log.level)),
+                self.color,
+                Style::Color(color_for_level(log.level))
+            ),
+            msg = log.message
+        )?;
+        let location = format!(
+            "└── File: {}, Line number: {}",
+            log.file_name, log.line_number
+        );
+        writeln!(writer, "{}", colorize(&location, self.color, Style::Dimmed))
+    }
+}
+
+/// One JSON object per line, with all the fields of the log.
+#[cfg(feature = "serde")]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct JsonFormatter;
+
+#[cfg(feature = "serde")]
+impl LogFormatter for JsonFormatter {
+    fn format(&self, log: &Log, writer: &mut dyn Write) -> io::Result<()> {
+        serde_json::to_writer(&mut *writer, log)?;
+        writeln!(writer)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+/// Formatting options of a template field, following the syntax of Rust format specs:
+/// `[[fill]align][0][width][.precision]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct FieldSpec {
+    fill: char,
+    align: Option<Align>,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl FieldSpec {
+    fn parse(spec: &str) -> Option<Self> {
+        fn to_align(c: char) -> Option<Align> {
+            match c {
+                '<' => Some(Align::Left),
+                '>' => Some(Align::Right),
+                '^' => Some(Align::Center),
+                _ => None,
+            }
+        }
+
+        fn take_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<usize> {
+            let mut digits = String::new();
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                digits.push(digit);
+            }
+            digits.parse().ok()
+        }
+
+        let mut chars = spec.chars().peekable();
+        let mut fill = ' ';
+        let mut align = None;
+        let mut lookahead = spec.chars();
+        match (lookahead.next(), lookahead.next()) {
+            (Some(first), Some(second)) if to_align(second).is_some() => {
+                fill = first;
+                align = to_align(second);
+                chars.nth(1);
+            }
+            (Some(first), _) if to_align(first).is_some() => {
+                align = to_align(first);
+                chars.next();
+            }
+            _ => {}
+        }
+
+        // Zero padding, as in `{line:04}`
+        if align.is_none() && chars.next_if_eq(&'0').is_some() {
+            fill = '0';
+            align = Some(Align::Right);
+        }
+        let width = take_number(&mut chars).unwrap_or(0);
+        let precision = match chars.next() {
+            Some('.') => Some(take_number(&mut chars)?),
+            Some(_) => return None,
+            None => None,
+        };
+        if chars.next().is_some() {
+            return None;
+        }
+
+        Some(Self {
+            fill,
+            align,
+            width,
+            precision,
+        })
+    }
+
+    /// Pads `value` up to the width of the field.
+    fn pad(&self, value: &str, default_align: Align) -> String {
+        let padding = self.width.saturating_sub(value.chars().count());
+        let (left, right) = match self.align.unwrap_or(default_align) {
+            Align::Left => (0, padding),
+            Align::Right => (padding, 0),
+            Align::Center => (padding / 2, padding - padding / 2),
+        };
+        let fill = |count| self.fill.to_string().repeat(count);
+        format!("{}{}{}", fill(left), value, fill(right))
+    }
+
+    /// Truncates `value` to the precision of the field and pads it.
+    fn pad_str(&self, value: &str) -> String {
+        match self.precision {
+            Some(precision) => {
+                let truncated: String = value.chars().take(precision).collect();
+                self.pad(&truncated, Align::Left)
+            }
+            None => self.pad(value, Align::Left),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Field {
+    TargetTime,
+    HostTime,
+    Channel,
+    Core,
+    Level,
+    File,
+    Line,
+    Message,
+    Format,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "t" | "time" => Some(Field::TargetTime),
+            "T" | "host_time" => Some(Field::HostTime),
+            "C" | "channel" => Some(Field::Channel),
+            "core" => Some(Field::Core),
+            "L" | "level" => Some(Field::Level),
+            "file" => Some(Field::File),
+            "line" => Some(Field::Line),
+            "msg" => Some(Field::Message),
+            "fmt" => Some(Field::Format),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Field(Field, FieldSpec),
+}
+
+/// Renders each log following a template, where fields between braces are replaced by the values
+/// of the log. A field can be followed by a format spec like in Rust format strings, with fill,
+/// alignment, width and precision, e.g. `{t:>10.3}` or `{L:<7}`.
+///
+/// The available fields are:
+///
+/// - `t` or `time`: target timestamp in seconds, with 6 decimals unless a precision is given.
+/// - `T` or `host_time`: host wall-clock time at which the log was received, or `-` if unknown.
+/// - `C` or `channel`: name of the channel the log was received through, or `-` if unknown.
+/// - `core`: index of the target core the log was received from, or `-` if unknown.
+/// - `L` or `level`: log level, colored like in the default layout.
+/// - `file` and `line`: file name and line number of the log.
+/// - `msg`: formatted message.
+/// - `fmt`: format string of the message.
+///
+/// Literal braces are written as `{{` and `}}`, and `\n` and `\t` are replaced by a new line and a
+/// tab respectively. A new line is always appended after the rendered template.
+///
+/// ```
+/// use postform_decoder::TemplateFormatter;
+/// let formatter: TemplateFormatter = "{t:>10.3} {L:<7} {file}:{line} {msg}".parse().unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TemplateFormatter {
+    segments: Vec<Segment>,
+    color: bool,
+}
+
+impl std::str::FromStr for TemplateFormatter {
+    type Err = Error;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let error =
+            |reason: &str| Error::InvalidLogTemplate(format!("{} in \"{}\"", reason, template));
+        let mut segments = vec![];
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '}' => return Err(error("unmatched '}'")),
+                '\\' if chars.as_str().starts_with('n') => {
+                    chars.next();
+                    literal.push('\n');
+                }
+                '\\' if chars.as_str().starts_with('t') => {
+                    chars.next();
+                    literal.push('\t');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest.find('}').ok_or_else(|| error("unclosed '{'"))?;
+                    let (name, spec) = rest[..end].split_once(':').unwrap_or((&rest[..end], ""));
+                    let field = Field::parse(name)
+                        .ok_or_else(|| error(&format!("unknown field \"{}\"", name)))?;
+                    let spec = FieldSpec::parse(spec)
+                        .ok_or_else(|| error(&format!("invalid format spec \"{}\"", spec)))?;
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Field(field, spec));
+                    chars = rest[end + 1..].chars();
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Self {
+            segments,
+            color: default_color(),
+        })
+    }
+}
+
+impl TemplateFormatter {
+    /// Enables or disables the color of the level field. By default the level is colored when
+    /// stdout is a terminal, unless disabled through the environment, e.g. with `NO_COLOR`.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    fn render_field(&self, field: Field, spec: &FieldSpec, log: &Log) -> String {
+        match field {
+            Field::TargetTime => {
+                let precision = spec.precision.unwrap_or(6);
+                spec.pad(&format!("{:.*}", precision, log.timestamp), Align::Right)
+            }
+            Field::HostTime => spec.pad_str(
+                &log.host_timestamp
+                    .map(|timestamp| humantime::format_rfc3339_micros(timestamp).to_string())
+                    .unwrap_or_else(|| "-".to_owned()),
+            ),
+            Field::Channel => spec.pad_str(log.channel.as_deref().unwrap_or("-")),
+            Field::Core => spec.pad(
+                &log.core
+                    .map_or_else(|| "-".to_owned(), |core| core.to_string()),
+                Align::Right,
+            ),
+            Field::Level => colorize(
+                &spec.pad_str(level_name(log.level)),
+                self.color,
+                Style::Color(color_for_level(log.level)),
+            ),
+            Field::File => spec.pad_str(&log.file_name),
+            Field::Line => spec.pad(&log.line_number.to_string(), Align::Right),
+            Field::Message => spec.pad_str(&log.message),
+            Field::Format => spec.pad_str(&log.format),
+        }
+    }
+}
+
+impl LogFormatter for TemplateFormatter {
+    fn format(&self, log: &Log, writer: &mut dyn Write) -> io::Result<()> {
+        let mut line = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(literal) => line.push_str(literal),
+                Segment::Field(field, spec) => line.push_str(&self.render_field(*field, spec, log)),
+            }
+        }
+        writeln!(writer, "{}", line)
+    }
+}
+
+/// Reads a log from buffer and prints it to stdout
+///
+/// # Panics
+///
+/// Panics if writing to stdout fails, like `println!`.
+pub fn print_log(log: &Log) {
+    if let Err(error) = DefaultFormatter::default().format(log, &mut io::stdout().lock()) {
+        panic!("failed printing to stdout: {}", error);
+    }
+}
+
+/// Selects how decoded logs are printed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human readable output, as printed by [`DefaultFormatter`] or [`TemplateFormatter`].
+    Text,
+    /// One JSON object per log, as printed by [`JsonFormatter`].
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+/// Error returned when parsing an unknown [`OutputFormat`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid output format \"{0}\"")]
+pub struct ParseOutputFormatError(String);
+
+impl std::str::FromStr for OutputFormat {
+    type Err = ParseOutputFormatError;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "text" => Ok(OutputFormat::Text),
+            #[cfg(feature = "serde")]
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(ParseOutputFormatError(format.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn create_log() -> Log {
+        Log {
+            timestamp: 1.5,
+            host_timestamp: None,
+            channel: None,
+            core: None,
+            level: LogLevel::Warning,
+            message: "Temperature: 23.50".to_owned(),
+            file_name: "main.cpp".into(),
+            line_number: 12,
+            format: "Temperature: %.2f".into(),
+            args: vec![],
+        }
+    }
+
+    fn render(formatter: &dyn LogFormatter, log: &Log) -> String {
+        let mut output = vec![];
+        formatter.format(log, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    fn render_template(template: &str, log: &Log) -> String {
+        render(
+            &template.parse::<TemplateFormatter>().unwrap().color(false),
+            log,
+        )
+    }
+
+    #[test]
+    fn test_default_formatter() {
+        let mut log = create_log();
+        assert_eq!(
+            render(&DefaultFormatter::default().color(false), &log),
+            "1.500000     Warning    : Temperature: 23.50\n└── File: main.cpp, Line number: 12\n"
+        );
+
+        log.host_timestamp = Some(UNIX_EPOCH + Duration::from_micros(1_650_000_000_123_456));
+        let output = render(
+            &DefaultFormatter::new(TimestampFormat::Both).color(false),
+            &log,
+        );
+        assert!(output.starts_with("2022-04-15T05:20:00.123456Z 1.500000     Warning    :"));
+
+        log.channel = Some("Radio".to_owned());
+        let output = render(&DefaultFormatter::default().color(false), &log);
+        assert!(output.starts_with("1.500000     [Radio] Warning    : Temperature: 23.50\n"));
+
+        log.core = Some(1);
+        let output = render(&DefaultFormatter::default().color(false), &log);
+        assert!(output.starts_with("1.500000     [Radio] Warning    :"));
+        let output = render(
+            &DefaultFormatter::default().color(false).show_core(true),
+            &log,
+        );
+        assert!(output.starts_with("1.500000     [core 1] [Radio] Warning    :"));
+    }
+
+    #[test]
+    fn test_template_formatter() {
+        let mut log = create_log();
+        assert_eq!(
+            render_template("{t:>10.3} {L:<7} {file}:{line} {msg}", &log),
+            "     1.500 Warning main.cpp:12 Temperature: 23.50\n"
+        );
+        assert_eq!(
+            render_template("[{level:^9}] {{{msg:.11}}}\\n\\t{fmt}", &log),
+            "[ Warning ] {Temperature}\n\tTemperature: %.2f\n"
+        );
+        assert_eq!(
+            render_template("{t} {line:04} {file:*>10} {T}", &log),
+            "1.500000 0012 **main.cpp -\n"
+        );
+
+        log.host_timestamp = Some(UNIX_EPOCH + Duration::from_secs(1_650_000_000));
+        assert_eq!(render_template("{T:.19}", &log), "2022-04-15T05:20:00\n");
+
+        assert_eq!(
+            render_template("{C:<6}|{msg}", &log),
+            "-     |Temperature: 23.50\n"
+        );
+        log.channel = Some("Radio".to_owned());
+        assert_eq!(render_template("{channel:<6}|", &log), "Radio |\n");
+
+        assert_eq!(render_template("{core}|", &log), "-|\n");
+        log.core = Some(1);
+        assert_eq!(render_template("{core:3}|", &log), "  1|\n");
+    }
+
+    #[test]
+    fn test_formatter_colors() {
+        let log = create_log();
+        let template: TemplateFormatter = "{L:<8}|".parse().unwrap();
+        assert_eq!(
+            render(&template.clone().color(true), &log),
+            "\x1b[38;2;255;165;0mWarning \x1b[0m|\n"
+        );
+        assert_eq!(render(&template.color(false), &log), "Warning |\n");
+
+        let output = render(&DefaultFormatter::default().color(true), &log);
+        assert!(output.contains("\x1b[38;2;255;165;0mWarning    \x1b[0m"));
+        assert!(output.contains("\x1b[2m└── File: main.cpp, Line number: 12\x1b[0m"));
+        let output = render(&DefaultFormatter::default().color(false), &log);
+        assert!(!output.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_output_format_from_str() {
+        assert_eq!("text".parse(), Ok(OutputFormat::Text));
+        #[cfg(feature = "serde")]
+        assert_eq!("json".parse(), Ok(OutputFormat::Json));
+        assert_eq!(
+            "yaml".parse::<OutputFormat>().unwrap_err().to_string(),
+            "Invalid output format \"yaml\""
+        );
+    }
+
+    #[test]
+    fn test_timestamp_format_from_str() {
+        assert_eq!("target".parse(), Ok(TimestampFormat::Target));
+        assert_eq!("host".parse(), Ok(TimestampFormat::Host));
+        assert_eq!("both".parse(), Ok(TimestampFormat::Both));
+        assert!("local".parse::<TimestampFormat>().is_err());
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        for template in ["{t", "t}", "{unknown}", "{t:>x}", "{t:.}", "{msg:<5.2x}"] {
+            assert!(
+                matches!(
+                    template.parse::<TemplateFormatter>(),
+                    Err(Error::InvalidLogTemplate(_))
+                ),
+                "{}",
+                template
+            );
+        }
+    }
+}
@@ -1,7 +1,31 @@
-use byteorder::{LittleEndian, ReadBytesExt};
-use colored::Colorize;
+mod capture;
+mod dictionary;
+mod filter;
+mod format;
+mod formatter;
+
+pub use capture::{
+    elf_hash, is_capture, CaptureChunk, CaptureHeader, CaptureReader, CaptureWriter,
+};
+pub use filter::{FileGlob, LineRange, LogFilter};
+#[cfg(feature = "serde")]
+pub use formatter::JsonFormatter;
+pub use formatter::{
+    print_log, DefaultFormatter, LogFormatter, OutputFormat, ParseOutputFormatError,
+    ParseTimestampFormatError, TemplateFormatter, TimestampFormat,
+};
+
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt};
+use format::FormatSpec;
 use object::read::{File as ElfFile, Object, ObjectSection, ObjectSymbol};
-use std::{fs, path::Path};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fs,
+    io::Read,
+    path::Path,
+    sync::Arc,
+    time::SystemTime,
+};
 
 include!(concat!(env!("OUT_DIR"), "/version.rs"));
 
@@ -32,16 +56,33 @@ pub enum Error {
     LevelNotFound,
     #[error("Invalid format string")]
     InvalidFormatString,
+    #[error("Interned string pointer out of bounds: {0:#x}")]
+    InvalidInternedStringPointer(usize),
     #[error("Invalid log message")]
     InvalidLogMessage,
     #[error("Missing log argument")]
     MissingLogArgument,
     #[error("Invalid format specifier: '{0}'")]
     InvalidFormatSpecifier(char),
+    #[error("Invalid log template: {0}")]
+    InvalidLogTemplate(String),
+    #[error("Invalid log level \"{0}\"")]
+    InvalidLogLevel(String),
+    #[error("Invalid filter \"{0}\"")]
+    InvalidFilter(String),
+    #[error("Invalid metadata dictionary")]
+    InvalidDictionary,
+    #[error("Truncated record, {0} bytes left at the end of the stream")]
+    TruncatedRecord(usize),
+    #[error("Invalid capture file")]
+    InvalidCapture,
+    #[error("The capture doesn't embed a metadata dictionary")]
+    MissingEmbeddedDictionary,
 }
 
-/// Available log levels of Postform.
-#[derive(Copy, Clone, Debug, strum_macros::IntoStaticStr)]
+/// Available log levels of Postform, ordered by severity.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, strum_macros::IntoStaticStr)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LogLevel {
     Debug,
     Info,
@@ -67,13 +108,13 @@ impl LogLevel {
     fn find_level_in_elf(&self, elf_file: &ElfFile) -> Result<(usize, usize), Error> {
         let start = elf_file
             .symbols()
-            .find(|x| x.name().unwrap() == self.get_start_section_label())
+            .find(|x| x.name() == Ok(&self.get_start_section_label()))
             .map(|section| section.address() as usize)
             .ok_or(Error::LevelNotFound)?;
 
         let end = elf_file
             .symbols()
-            .find(|x| x.name().unwrap() == self.get_end_section_label())
+            .find(|x| x.name() == Ok(&self.get_end_section_label()))
             .map(|section| section.address() as usize)
             .ok_or(Error::LevelNotFound)?;
 
@@ -81,19 +122,159 @@ impl LogLevel {
     }
 }
 
+impl std::str::FromStr for LogLevel {
+    type Err = Error;
+
+    fn from_str(level: &str) -> Result<Self, Self::Err> {
+        match level.to_ascii_lowercase().as_str() {
+            "debug" => Ok(LogLevel::Debug),
+            "info" => Ok(LogLevel::Info),
+            "warning" | "warn" => Ok(LogLevel::Warning),
+            "error" => Ok(LogLevel::Error),
+            _ => Err(Error::InvalidLogLevel(level.to_owned())),
+        }
+    }
+}
+
 struct LogSection {
     level: LogLevel,
     start: usize,
     end: usize,
 }
 
+/// Value of a log argument, as decoded from the message sent by the target.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ArgValue {
+    /// A signed integer (`%d`, `%i`).
+    Signed(i64),
+    /// An unsigned integer (`%u`, `%o`, `%x`, `%X`).
+    Unsigned(u64),
+    /// A floating point number (`%f`, `%e`, `%g`, `%a`).
+    #[cfg_attr(feature = "serde", serde(with = "non_finite_float"))]
+    Float(f64),
+    /// A character (`%c`).
+    Char(char),
+    /// A string sent along with the log (`%s`).
+    String(String),
+    /// A pointer (`%p`).
+    Pointer(u64),
+    /// A string interned in the firmware ELF (`%k`).
+    InternedString(String),
+}
+
+fn check_postform_version(version: &str, disable_version_check: bool) -> Result<(), Error> {
+    if !disable_version_check && version != POSTFORM_VERSION {
+        return Err(Error::MismatchedPostformVersions(
+            version.to_string(),
+            POSTFORM_VERSION.to_string(),
+        ));
+    }
+    Ok(())
+}
+
 /// Representation of a parsed Postform log.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Log {
     pub timestamp: f64,
+    /// Host time at which the log was received, for logs decoded from a live transport or a
+    /// capture.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            with = "rfc3339_timestamp"
+        )
+    )]
+    pub host_timestamp: Option<SystemTime>,
+    /// Name of the channel the log was received through, when decoding several channels of the
+    /// same target.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub channel: Option<String>,
+    /// Index of the target core the log was received from, if known.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub core: Option<usize>,
     pub level: LogLevel,
     pub message: String,
-    pub file_name: String,
+    /// Name of the file of the log call. Shared by all the logs of the same call.
+    pub file_name: Arc<str>,
     pub line_number: u32,
+    /// Format string of the log, without the file name and line number. Shared by all the logs of
+    /// the same call.
+    pub format: Arc<str>,
+    /// Decoded values of the arguments, in the same order as in the format string.
+    pub args: Vec<ArgValue>,
+}
+
+/// Serializes host timestamps as RFC 3339 strings, which are easier to consume than the default
+/// representation of SystemTime.
+#[cfg(feature = "serde")]
+mod rfc3339_timestamp {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::time::SystemTime;
+
+    pub fn serialize<S: Serializer>(
+        timestamp: &Option<SystemTime>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match timestamp {
+            Some(timestamp) => {
+                serializer.serialize_some(&humantime::format_rfc3339_micros(*timestamp).to_string())
+            }
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<SystemTime>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|timestamp| humantime::parse_rfc3339(&timestamp).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// Serializes floats that JSON can't represent, NaN and the infinities, as the strings "NaN",
+/// "inf" and "-inf".
+#[cfg(feature = "serde")]
+mod non_finite_float {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Float {
+        Number(f64),
+        NonFinite(String),
+    }
+
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        if value.is_nan() {
+            serializer.serialize_str("NaN")
+        } else if value.is_infinite() {
+            serializer.serialize_str(if *value > 0.0 { "inf" } else { "-inf" })
+        } else {
+            serializer.serialize_f64(*value)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        match Float::deserialize(deserializer)? {
+            Float::Number(value) => Ok(value),
+            Float::NonFinite(value) => match value.as_str() {
+                "NaN" => Ok(f64::NAN),
+                "inf" => Ok(f64::INFINITY),
+                "-inf" => Ok(f64::NEG_INFINITY),
+                _ => Err(D::Error::custom(format!("invalid float \"{}\"", value))),
+            },
+        }
+    }
 }
 
 /// The ElfMetadata struct encapsulates all log metadata contained in the target ELF file.
@@ -119,16 +300,52 @@ pub struct Log {
 /// }
 /// ```
 pub struct ElfMetadata {
+    postform_version: String,
     timestamp_freq: f64,
     strings: Vec<u8>,
     log_sections: Vec<LogSection>,
 }
 
 impl ElfMetadata {
+    /// Attempts to instantiate the ElfMetadata struct from the provided file, which can be an ELF
+    /// file, a metadata dictionary exported with
+    /// [`write_dictionary`](ElfMetadata::write_dictionary) or a capture with an embedded
+    /// dictionary.
+    pub fn from_file(path: &Path, disable_version_check: bool) -> Result<Self, Error> {
+        Self::from_bytes(&fs::read(path)?, disable_version_check)
+    }
+
+    /// Attempts to instantiate the ElfMetadata struct from an in-memory ELF image, metadata
+    /// dictionary or capture with an embedded dictionary.
+    pub fn from_bytes(data: &[u8], disable_version_check: bool) -> Result<Self, Error> {
+        if Self::is_dictionary(data) {
+            Self::from_dictionary(data, disable_version_check)
+        } else if is_capture(data) {
+            CaptureReader::new(data)?
+                .header()
+                .elf_metadata(disable_version_check)?
+                .ok_or(Error::MissingEmbeddedDictionary)
+        } else {
+            Self::from_elf_bytes(data, disable_version_check)
+        }
+    }
+
     /// Attempts to instantiate the ElfMetadata struct from the provided ELF file.
     pub fn from_elf_file(elf_path: &Path, disable_version_check: bool) -> Result<Self, Error> {
-        let file_contents = fs::read(elf_path)?;
-        let elf_file = ElfFile::parse(&file_contents[..])?;
+        Self::from_elf_bytes(&fs::read(elf_path)?, disable_version_check)
+    }
+
+    /// Attempts to instantiate the ElfMetadata struct from an ELF image read to completion from
+    /// `reader`.
+    pub fn from_reader<R: Read>(mut reader: R, disable_version_check: bool) -> Result<Self, Error> {
+        let mut elf_data = vec![];
+        reader.read_to_end(&mut elf_data)?;
+        Self::from_elf_bytes(&elf_data, disable_version_check)
+    }
+
+    /// Attempts to instantiate the ElfMetadata struct from an ELF image held in memory.
+    pub fn from_elf_bytes(elf_data: &[u8], disable_version_check: bool) -> Result<Self, Error> {
+        let elf_file = ElfFile::parse(elf_data)?;
 
         let postform_version = elf_file
             .section_by_name(".postform_version")
@@ -140,13 +357,9 @@ impl ElfMetadata {
                 .iter()
                 .position(|&c| c == b'\0')
                 .unwrap_or(postform_version.len())],
-        );
-        if !disable_version_check && postform_version != POSTFORM_VERSION {
-            return Err(Error::MismatchedPostformVersions(
-                postform_version.to_string(),
-                POSTFORM_VERSION.to_string(),
-            ));
-        }
+        )
+        .to_string();
+        check_postform_version(&postform_version, disable_version_check)?;
         let interned_strings = elf_file
             .section_by_name(".interned_strings")
             .ok_or(Error::MissingInternedStrings)?
@@ -177,6 +390,7 @@ impl ElfMetadata {
         }
 
         Ok(Self {
+            postform_version,
             timestamp_freq,
             strings: interned_strings.into(),
             log_sections: sections,
@@ -200,7 +414,10 @@ impl ElfMetadata {
     }
 
     fn recover_interned_string(&self, str_ptr: usize) -> Result<String, Error> {
-        let str_buffer = &self.strings[str_ptr..];
+        let str_buffer = self
+            .strings
+            .get(str_ptr..)
+            .ok_or(Error::InvalidInternedStringPointer(str_ptr))?;
         let end_of_string = str_buffer
             .iter()
             .position(|&c| c == b'\0')
@@ -209,7 +426,8 @@ impl ElfMetadata {
     }
 }
 
-type FormatSpecHandler = for<'a> fn(&Decoder, &mut String, &'_ mut &'a [u8]) -> Result<(), Error>;
+type ConversionHandler =
+    for<'a> fn(&ElfMetadata, &FormatSpec, &'_ mut &'a [u8]) -> Result<ArgValue, Error>;
 
 fn decode_unsigned(message: &'_ mut &'_ [u8]) -> Result<u64, Error> {
     leb128::read::unsigned(message).map_err(|_| Error::InvalidLogMessage)
@@ -219,283 +437,751 @@ fn decode_signed(message: &'_ mut &'_ [u8]) -> Result<i64, Error> {
     leb128::read::signed(message).map_err(|_| Error::InvalidLogMessage)
 }
 
-fn format_char(out_str: &mut String, buffer: &mut &[u8]) -> Result<(), Error> {
+fn decode_char_arg(buffer: &mut &[u8]) -> Result<ArgValue, Error> {
     let integer_val = decode_unsigned(buffer)?;
     let char_val = char::from_u32(integer_val as u32).ok_or(Error::InvalidLogMessage)?;
-    let integer_str = format!("{}", char_val);
-    out_str.push_str(&integer_str);
-    Ok(())
+    Ok(ArgValue::Char(char_val))
 }
 
-fn format_unsigned(out_str: &mut String, buffer: &mut &[u8]) -> Result<(), Error> {
+fn decode_unsigned_arg(spec: &FormatSpec, buffer: &mut &[u8]) -> Result<ArgValue, Error> {
     let integer_val = decode_unsigned(buffer)?;
-    let integer_str = format!("{}", integer_val);
-    out_str.push_str(&integer_str);
-    Ok(())
+    Ok(ArgValue::Unsigned(spec.truncate_unsigned(integer_val)))
 }
 
-fn format_signed(out_str: &mut String, buffer: &mut &[u8]) -> Result<(), Error> {
+fn decode_signed_arg(spec: &FormatSpec, buffer: &mut &[u8]) -> Result<ArgValue, Error> {
     let integer_val = decode_signed(buffer)?;
-    let integer_str = format!("{}", integer_val);
-    out_str.push_str(&integer_str);
-    Ok(())
+    Ok(ArgValue::Signed(spec.truncate_signed(integer_val)))
 }
 
-fn format_octal(out_str: &mut String, buffer: &mut &[u8]) -> Result<(), Error> {
-    let integer_val = decode_unsigned(buffer)?;
-    let integer_str = format!("{:o}", integer_val);
-    out_str.push_str(&integer_str);
-    Ok(())
+fn decode_float_arg(buffer: &mut &[u8]) -> Result<ArgValue, Error> {
+    let float_val = buffer
+        .read_f64::<LittleEndian>()
+        .map_err(|_| Error::InvalidLogMessage)?;
+    Ok(ArgValue::Float(float_val))
 }
 
-fn format_hex(out_str: &mut String, buffer: &mut &[u8]) -> Result<(), Error> {
+fn decode_pointer_arg(buffer: &mut &[u8]) -> Result<ArgValue, Error> {
     let integer_val = decode_unsigned(buffer)?;
-    let integer_str = format!("{:x}", integer_val);
-    out_str.push_str(&integer_str);
-    Ok(())
-}
-
-fn format_pointer(out_str: &mut String, buffer: &mut &[u8]) -> Result<(), Error> {
-    let integer_val = decode_unsigned(buffer)?;
-    let integer_str = format!("0x{:x}", integer_val);
-    out_str.push_str(&integer_str);
-    Ok(())
+    Ok(ArgValue::Pointer(integer_val))
 }
 
-const FORMAT_SPEC_TABLE: [(&str, FormatSpecHandler); 25] = [
-    ("%s", |_, out_str, buffer| {
+const CONVERSION_TABLE: [(char, ConversionHandler); 18] = [
+    ('s', |_, _, buffer| {
         let nul_range_end = buffer
             .iter()
             .position(|&c| c == b'\0')
-            .unwrap_or(buffer.len());
+            .ok_or(Error::MissingLogArgument)?;
 
         let res =
             std::str::from_utf8(&buffer[..nul_range_end]).or(Err(Error::MissingLogArgument))?;
         *buffer = &buffer[nul_range_end + 1..];
-        out_str.push_str(res);
-        Ok(())
+        Ok(ArgValue::String(res.to_owned()))
     }),
-    ("%hhd", |_, out_str, buffer| format_signed(out_str, buffer)),
-    ("%hd", |_, out_str, buffer| format_signed(out_str, buffer)),
-    ("%d", |_, out_str, buffer| format_signed(out_str, buffer)),
-    ("%ld", |_, out_str, buffer| format_signed(out_str, buffer)),
-    ("%lld", |_, out_str, buffer| format_signed(out_str, buffer)),
-    ("%hhu", |_, out_str, buffer| {
-        format_unsigned(out_str, buffer)
-    }),
-    ("%hu", |_, out_str, buffer| format_unsigned(out_str, buffer)),
-    ("%u", |_, out_str, buffer| format_unsigned(out_str, buffer)),
-    ("%lu", |_, out_str, buffer| format_unsigned(out_str, buffer)),
-    ("%llu", |_, out_str, buffer| {
-        format_unsigned(out_str, buffer)
-    }),
-    ("%hho", |_, out_str, buffer| format_octal(out_str, buffer)),
-    ("%ho", |_, out_str, buffer| format_octal(out_str, buffer)),
-    ("%o", |_, out_str, buffer| format_octal(out_str, buffer)),
-    ("%lo", |_, out_str, buffer| format_octal(out_str, buffer)),
-    ("%llo", |_, out_str, buffer| format_octal(out_str, buffer)),
-    ("%hhx", |_, out_str, buffer| format_hex(out_str, buffer)),
-    ("%hx", |_, out_str, buffer| format_hex(out_str, buffer)),
-    ("%x", |_, out_str, buffer| format_hex(out_str, buffer)),
-    ("%lx", |_, out_str, buffer| format_hex(out_str, buffer)),
-    ("%llx", |_, out_str, buffer| format_hex(out_str, buffer)),
-    ("%p", |_, out_str, buffer| format_pointer(out_str, buffer)),
-    ("%k", |decoder, out_str, buffer| {
+    ('d', |_, spec, buffer| decode_signed_arg(spec, buffer)),
+    ('i', |_, spec, buffer| decode_signed_arg(spec, buffer)),
+    ('u', |_, spec, buffer| decode_unsigned_arg(spec, buffer)),
+    ('o', |_, spec, buffer| decode_unsigned_arg(spec, buffer)),
+    ('x', |_, spec, buffer| decode_unsigned_arg(spec, buffer)),
+    ('X', |_, spec, buffer| decode_unsigned_arg(spec, buffer)),
+    ('p', |_, _, buffer| decode_pointer_arg(buffer)),
+    ('k', |elf_metadata, _, buffer| {
         let str_ptr = decode_unsigned(buffer)? as usize;
-        let interned_string = decoder.elf_metadata.recover_interned_string(str_ptr)?;
-        out_str.push_str(&interned_string);
-        Ok(())
-    }),
-    ("%%", |_, out_str, _| {
-        out_str.push('%');
-        Ok(())
+        let interned_string = elf_metadata.recover_interned_string(str_ptr)?;
+        Ok(ArgValue::InternedString(interned_string))
     }),
-    ("%c", |_, out_str, buffer| format_char(out_str, buffer)),
+    ('c', |_, _, buffer| decode_char_arg(buffer)),
+    ('f', |_, _, buffer| decode_float_arg(buffer)),
+    ('F', |_, _, buffer| decode_float_arg(buffer)),
+    ('e', |_, _, buffer| decode_float_arg(buffer)),
+    ('E', |_, _, buffer| decode_float_arg(buffer)),
+    ('g', |_, _, buffer| decode_float_arg(buffer)),
+    ('G', |_, _, buffer| decode_float_arg(buffer)),
+    ('a', |_, _, buffer| decode_float_arg(buffer)),
+    ('A', |_, _, buffer| decode_float_arg(buffer)),
 ];
 
+/// A piece of a format string, parsed once per log site.
+enum FormatToken {
+    /// Text that is copied verbatim to the formatted message.
+    Literal(String),
+    /// A conversion specification, along with the handler that decodes and renders it.
+    Conversion(FormatSpec, ConversionHandler),
+}
+
+/// Splits a format string into literal text and conversion specifications.
+fn tokenize_format_string(mut format: &str) -> Result<Vec<FormatToken>, Error> {
+    let mut tokens = vec![];
+    let mut literal = String::new();
+    while let Some(format_spec_pos) = format.find('%') {
+        // Push characters until the %
+        literal.push_str(&format[..format_spec_pos]);
+        // Advance the format string past the %
+        format = &format[format_spec_pos + 1..];
+
+        let (format_spec, format_spec_len) = FormatSpec::parse(format)?;
+        if format_spec.conversion == '%' {
+            literal.push('%');
+        } else {
+            let (_, handler) = CONVERSION_TABLE
+                .iter()
+                .find(|(conversion, _)| *conversion == format_spec.conversion)
+                .ok_or(Error::InvalidFormatSpecifier(format_spec.conversion))?;
+            if !literal.is_empty() {
+                tokens.push(FormatToken::Literal(std::mem::take(&mut literal)));
+            }
+            tokens.push(FormatToken::Conversion(format_spec, *handler));
+        }
+        // Advance the format string past the format specifier
+        format = &format[format_spec_len..];
+    }
+
+    // Insert what's left of the format string
+    literal.push_str(format);
+    if !literal.is_empty() {
+        tokens.push(FormatToken::Literal(literal));
+    }
+    Ok(tokens)
+}
+
+/// Splits an interned string into its file name, line number and format string.
+fn decode_format_string(interned_string: &str) -> Result<(&str, u32, &str), Error> {
+    // The format string itself may contain '@' characters, so split at most 3 times.
+    let mut splits = interned_string.splitn(3, '@');
+
+    let file_name = splits.next().ok_or(Error::InvalidFormatString)?;
+    let line_number = splits
+        .next()
+        .ok_or(Error::InvalidFormatString)?
+        .parse()
+        .or(Err(Error::InvalidFormatString))?;
+    let format = splits.next().ok_or(Error::InvalidFormatString)?;
+
+    Ok((file_name, line_number, format))
+}
+
+/// All the information about a log call in the firmware that can be obtained from its interned
+/// format string.
+struct LogSite {
+    level: LogLevel,
+    file_name: Arc<str>,
+    line_number: u32,
+    format: Arc<str>,
+    tokens: Vec<FormatToken>,
+}
+
+impl LogSite {
+    fn parse(elf_metadata: &ElfMetadata, str_ptr: usize) -> Result<Self, Error> {
+        let interned_string = elf_metadata.recover_interned_string(str_ptr)?;
+        let (file_name, line_number, format) = decode_format_string(&interned_string)?;
+        Ok(LogSite {
+            level: elf_metadata.get_log_section(str_ptr).level,
+            file_name: file_name.into(),
+            line_number,
+            format: format.into(),
+            tokens: tokenize_format_string(format)?,
+        })
+    }
+
+    /// Decodes the arguments of the log and renders them into the formatted message.
+    fn render(
+        &self,
+        elf_metadata: &ElfMetadata,
+        mut arguments: &[u8],
+    ) -> Result<(String, Vec<ArgValue>), Error> {
+        let mut formatted_str = String::new();
+        let mut args = vec![];
+        for token in &self.tokens {
+            match token {
+                FormatToken::Literal(literal) => formatted_str.push_str(literal),
+                FormatToken::Conversion(format_spec, handler) => {
+                    let arg = handler(elf_metadata, format_spec, &mut arguments)?;
+                    format_spec.render(&mut formatted_str, &arg);
+                    args.push(arg);
+                }
+            }
+        }
+        Ok((formatted_str, args))
+    }
+}
+
 /// Decodes Postform logs from the ElfMetadata and a buffer.
+///
+/// The interned format string of each log site is parsed the first time a log from that site is
+/// decoded. Subsequent logs from the same site reuse the parsed format.
 pub struct Decoder<'a> {
     elf_metadata: &'a ElfMetadata,
+    log_sites: HashMap<usize, LogSite>,
 }
 
 impl<'a> Decoder<'a> {
     /// Creates a new Decoder that uses the borrowed ElfMetadata.
     pub fn new(elf_metadata: &'a ElfMetadata) -> Self {
-        Decoder { elf_metadata }
+        Decoder {
+            elf_metadata,
+            log_sites: HashMap::new(),
+        }
     }
 
     /// Parses a Postform message from the passed buffer.
     /// If the buffer is invalid it may return an error.
     pub fn decode(&mut self, mut buffer: &[u8]) -> Result<Log, Error> {
+        let elf_metadata = self.elf_metadata;
         let timestamp = leb128::read::unsigned(&mut buffer).map_err(|_| Error::InvalidLogMessage)?
             as f64
-            / self.elf_metadata.timestamp_freq;
+            / elf_metadata.timestamp_freq;
 
         let str_ptr = decode_unsigned(&mut buffer)? as usize;
 
-        let format_string = self.elf_metadata.recover_interned_string(str_ptr)?;
-        let (file_name, line_number, format_str) = self.decode_format_string(format_string)?;
-        let formatted_str = self.format_string(&format_str, buffer)?;
-        let log_section = self.elf_metadata.get_log_section(str_ptr);
+        let log_site = match self.log_sites.entry(str_ptr) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(LogSite::parse(elf_metadata, str_ptr)?),
+        };
+        let (formatted_str, args) = log_site.render(elf_metadata, buffer)?;
 
         Ok(Log {
             timestamp,
-            level: log_section.level,
+            host_timestamp: None,
+            channel: None,
+            core: None,
+            level: log_site.level,
             message: formatted_str,
-            file_name,
-            line_number,
+            file_name: log_site.file_name.clone(),
+            line_number: log_site.line_number,
+            format: log_site.format.clone(),
+            args,
         })
     }
 
-    fn decode_format_string(
-        &self,
-        interned_string: String,
-    ) -> Result<(String, u32, String), Error> {
-        let mut splits = interned_string.split('@');
-
-        let file_name = splits.next().ok_or(Error::InvalidFormatString)?.to_owned();
-        let line_number = splits
-            .next()
-            .ok_or(Error::InvalidFormatString)?
-            .parse()
-            .or(Err(Error::InvalidFormatString))?;
-        let format = splits.next().ok_or(Error::InvalidFormatString)?.to_owned();
-
-        Ok((file_name, line_number, format))
+    /// Formats the arguments with a format string that is not cached.
+    #[cfg(test)]
+    fn format_string(&self, format: &str, arguments: &[u8]) -> Result<String, Error> {
+        let log_site = LogSite {
+            level: LogLevel::Unknown,
+            file_name: "".into(),
+            line_number: 0,
+            format: format.into(),
+            tokens: tokenize_format_string(format)?,
+        };
+        let (formatted_str, _) = log_site.render(self.elf_metadata, arguments)?;
+        Ok(formatted_str)
     }
+}
 
-    fn format_string(&self, format: &str, mut arguments: &[u8]) -> Result<String, Error> {
-        let mut format = String::from(format);
-        let mut formatted_str = String::new();
-        loop {
-            let format_spec_pos = match format.find('%') {
-                Some(pos) => pos,
-                None => {
-                    // Insert what's left of the format string and return;
-                    formatted_str.push_str(&format);
-                    return Ok(formatted_str);
-                }
-            };
-
-            // Push characters until the %
-            format
-                .chars()
-                .take(format_spec_pos)
-                .for_each(|c| formatted_str.push(c));
-            // Advance the format string
-            format = format.chars().skip(format_spec_pos).collect();
+/// Error produced by the SerialDecoder for a frame that could not be decoded. It carries the raw
+/// frame bytes as received, without the delimiter, unless the frame was too long to be kept.
+#[derive(Debug, thiserror::Error)]
+pub enum FrameError {
+    #[error("Error decoding rcobs log")]
+    Rcobs { frame: Vec<u8> },
+    #[error("Dropped frame of {len} bytes, longer than the maximum frame length")]
+    Oversized { len: usize },
+    #[error("Error parsing log: {error}")]
+    Decode {
+        frame: Vec<u8>,
+        #[source]
+        error: Error,
+    },
+}
 
-            let (format_spec, handler) = FORMAT_SPEC_TABLE
-                .iter()
-                .find(|(format_spec, _)| format.starts_with(format_spec))
-                .ok_or(Error::InvalidFormatSpecifier(
-                    format.chars().next().unwrap(),
-                ))?;
-            handler(self, &mut formatted_str, &mut arguments)?;
-            // Advance the format string past the format specifier
-            format = format.chars().skip(format_spec.len()).collect();
+impl FrameError {
+    /// Returns the raw bytes of the frame that could not be decoded, which are not available for
+    /// oversized frames.
+    pub fn frame(&self) -> &[u8] {
+        match self {
+            FrameError::Rcobs { frame } | FrameError::Decode { frame, .. } => frame,
+            FrameError::Oversized { .. } => &[],
         }
     }
 }
 
+/// Running statistics of a SerialDecoder.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SerialDecoderStats {
+    /// Number of frames decoded successfully.
+    pub frames_ok: u64,
+    /// Number of frames that could not be decoded or were too long.
+    pub frames_failed: u64,
+    /// Number of bytes dropped, either because they belong to failed frames or because they were
+    /// received before the first frame delimiter.
+    pub bytes_discarded: u64,
+}
+
 /// Decoder for logs sent through a SerialLogger in libpostform. Implements rcobs decoding and
 /// detection of start/end of message.
+///
+/// Frames longer than the maximum frame length are dropped, and the decoder resynchronizes on the
+/// next frame delimiter, where the dropped frame is reported as [`FrameError::Oversized`]. Since
+/// the stream may be joined in the middle of a frame, a first frame that can't be decoded is
+/// skipped silently.
 pub struct SerialDecoder<'a> {
     rcobs_msg_buffer: Vec<u8>,
+    max_frame_len: usize,
+    first_frame: bool,
+    /// Length of the oversized frame being dropped, if any.
+    oversized_len: Option<usize>,
+    stats: SerialDecoderStats,
+    channel: Option<String>,
+    core: Option<usize>,
     decoder: Decoder<'a>,
 }
 
 impl<'a> SerialDecoder<'a> {
+    /// Default maximum length of an rcobs encoded frame.
+    pub const DEFAULT_MAX_FRAME_LEN: usize = 4096;
+
     /// Creates a new SerialDecoder instance.
     pub fn new(elf_metadata: &'a ElfMetadata) -> Self {
+        Self::with_max_frame_len(elf_metadata, Self::DEFAULT_MAX_FRAME_LEN)
+    }
+
+    /// Creates a new SerialDecoder instance that drops frames longer than `max_frame_len` bytes.
+    pub fn with_max_frame_len(elf_metadata: &'a ElfMetadata, max_frame_len: usize) -> Self {
         Self {
             rcobs_msg_buffer: vec![],
+            max_frame_len,
+            first_frame: true,
+            oversized_len: None,
+            stats: SerialDecoderStats::default(),
+            channel: None,
+            core: None,
             decoder: Decoder::new(elf_metadata),
         }
     }
 
-    /// Feeds data to the decoder and accepts an action to trigger every time a message is decoded.
-    /// This action can then print the logs to stdout or do whatever the user prefers.
-    pub fn feed_and_do<T>(&mut self, data: &[u8], action: T)
+    /// Sets the name of the channel assigned to the decoded logs, to tell them apart from the logs
+    /// of other channels.
+    pub fn set_channel(&mut self, channel: Option<String>) {
+        self.channel = channel;
+    }
+
+    /// Sets the index of the target core assigned to the decoded logs, the one they are received
+    /// from.
+    pub fn set_core(&mut self, core: Option<usize>) {
+        self.core = core;
+    }
+
+    /// Returns the statistics of all the data fed to the decoder so far.
+    pub fn stats(&self) -> SerialDecoderStats {
+        self.stats
+    }
+
+    /// Drops any partially received frame and waits for the next frame delimiter, as if the
+    /// decoder had just been created. Used when the stream is interrupted, for instance when the
+    /// transport reconnects. The statistics are kept.
+    pub fn resync(&mut self) {
+        self.stats.bytes_discarded += self.rcobs_msg_buffer.len() as u64;
+        self.rcobs_msg_buffer.clear();
+        self.first_frame = true;
+        self.oversized_len = None;
+    }
+
+    /// Feeds data to the decoder and accepts an action to trigger every time a frame is received.
+    /// The action gets either the decoded log or the error that prevented decoding the frame, and
+    /// can then print the logs to stdout, report the errors or do whatever the user prefers.
+    pub fn feed_and_do<T>(&mut self, data: &[u8], mut action: T)
     where
-        T: Fn(Log),
+        T: FnMut(Result<Log, FrameError>),
     {
         for byte in data {
             if *byte == 0 {
-                match rcobs::decode(&self.rcobs_msg_buffer[..]) {
-                    Ok(message) => {
-                        match self.decoder.decode(&message[..]) {
-                            Ok(log) => {
-                                action(log);
-                            }
-                            Err(error) => {
-                                println!("{}{}", "Error parsing log: ".red(), error);
-                            }
-                        };
-                    }
-                    Err(_) => {
-                        println!("{}", "Error decoding rcobs log".red());
+                match self.oversized_len.take() {
+                    Some(len) if !self.first_frame => {
+                        self.stats.frames_failed += 1;
+                        action(Err(FrameError::Oversized { len }));
                     }
+                    Some(_) => {}
+                    None => self.end_frame(&mut action),
                 }
+                self.first_frame = false;
+                self.rcobs_msg_buffer.clear();
+            } else if let Some(len) = &mut self.oversized_len {
+                *len += 1;
+                self.stats.bytes_discarded += 1;
+            } else if self.rcobs_msg_buffer.len() >= self.max_frame_len {
+                self.stats.bytes_discarded += self.rcobs_msg_buffer.len() as u64 + 1;
+                self.oversized_len = Some(self.rcobs_msg_buffer.len() + 1);
                 self.rcobs_msg_buffer.clear();
             } else {
                 self.rcobs_msg_buffer.push(*byte);
             }
         }
     }
+
+    /// Same as [`feed_and_do`](SerialDecoder::feed_and_do), but sets the host timestamp of the
+    /// decoded logs to `host_timestamp`, usually the time at which the data was received.
+    pub fn feed_and_do_at<T>(&mut self, data: &[u8], host_timestamp: SystemTime, mut action: T)
+    where
+        T: FnMut(Result<Log, FrameError>),
+    {
+        self.feed_and_do(data, |log| {
+            action(log.map(|log| Log {
+                host_timestamp: Some(host_timestamp),
+                ..log
+            }))
+        });
+    }
+
+    fn end_frame<T>(&mut self, action: &mut T)
+    where
+        T: FnMut(Result<Log, FrameError>),
+    {
+        match self.decode_frame() {
+            Ok(log) => {
+                self.stats.frames_ok += 1;
+                action(Ok(Log {
+                    channel: self.channel.clone(),
+                    core: self.core,
+                    ..log
+                }));
+            }
+            Err(error) => {
+                self.stats.bytes_discarded += self.rcobs_msg_buffer.len() as u64;
+                if !self.first_frame {
+                    self.stats.frames_failed += 1;
+                    action(Err(error));
+                }
+            }
+        }
+    }
+
+    fn decode_frame(&mut self) -> Result<Log, FrameError> {
+        let frame = &self.rcobs_msg_buffer;
+        let message = rcobs::decode(frame).map_err(|_| FrameError::Rcobs {
+            frame: frame.clone(),
+        })?;
+        self.decoder
+            .decode(&message)
+            .map_err(|error| FrameError::Decode {
+                frame: frame.clone(),
+                error,
+            })
+    }
 }
 
-/// Returns the associated color for the log level
-fn color_for_level(level: LogLevel) -> colored::Color {
-    match level {
-        LogLevel::Debug => colored::Color::Green,
-        LogLevel::Info => colored::Color::Yellow,
-        LogLevel::Warning => colored::Color::TrueColor {
-            r: 0xFFu8,
-            g: 0xA5u8,
-            b: 0u8,
-        },
-        LogLevel::Error => colored::Color::Red,
-        LogLevel::Unknown => colored::Color::Red,
+/// Decoder for logs written by a FileLogger in libpostform. Each record is a little endian u32
+/// with the size of the log, followed by the log itself.
+///
+/// Records are read one at a time from the underlying reader, and the decoder can be used as an
+/// iterator over the decoded logs. A record cut short by the end of the stream is reported as an
+/// error.
+///
+/// ```
+/// use postform_decoder::{ElfMetadata, PersistDecoder};
+/// use std::{fs::File, io::BufReader, path::Path};
+/// fn postform_example(elf: &Path, log_file: &Path) {
+///     let elf_metadata = ElfMetadata::from_elf_file(elf, false).unwrap();
+///     let reader = BufReader::new(File::open(log_file).unwrap());
+///     for log in PersistDecoder::new(&elf_metadata, reader) {
+///         match log {
+///             Ok(log) => println!("{}: {}", log.timestamp, log.message),
+///             Err(error) => eprintln!("{}", error),
+///         }
+///     }
+/// }
+/// ```
+pub struct PersistDecoder<'a, R: Read> {
+    reader: R,
+    pending: Vec<u8>,
+    finished: bool,
+    decoder: Decoder<'a>,
+}
+
+impl<'a, R: Read> PersistDecoder<'a, R> {
+    const RECORD_HEADER_LEN: usize = std::mem::size_of::<u32>();
+
+    /// Creates a new PersistDecoder that reads records from `reader`.
+    pub fn new(elf_metadata: &'a ElfMetadata, reader: R) -> Self {
+        Self {
+            reader,
+            pending: vec![],
+            finished: false,
+            decoder: Decoder::new(elf_metadata),
+        }
+    }
+
+    /// Decodes the next record if it has been completely written to the underlying reader.
+    ///
+    /// Unlike the iterator, a partial record at the end of the stream is not an error. It is kept
+    /// until the next call, so this can be called again once more data is available, for
+    /// instance when following a file that is still being written.
+    pub fn next_available(&mut self) -> Option<Result<Log, Error>> {
+        match self.read_record() {
+            Ok(Some(record)) => Some(self.decoder.decode(&record)),
+            Ok(None) => None,
+            Err(error) => Some(Err(error)),
+        }
+    }
+
+    /// Replaces the underlying reader, dropping any partial record read from the previous one.
+    /// Useful when the followed file is truncated or rotated.
+    pub fn reset(&mut self, reader: R) {
+        self.reader = reader;
+        self.pending.clear();
+        self.finished = false;
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Reads from the underlying reader until there are at least `len` pending bytes. Returns
+    /// false if the reader ran out of data before that.
+    fn fill_pending(&mut self, len: usize) -> Result<bool, Error> {
+        while self.pending.len() < len {
+            let missing = (len - self.pending.len()) as u64;
+            if (&mut self.reader)
+                .take(missing)
+                .read_to_end(&mut self.pending)?
+                == 0
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Reads the next complete record. Partially read records are kept in the pending buffer.
+    fn read_record(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        if !self.fill_pending(Self::RECORD_HEADER_LEN)? {
+            return Ok(None);
+        }
+        let size = LittleEndian::read_u32(&self.pending) as usize;
+        if !self.fill_pending(Self::RECORD_HEADER_LEN + size)? {
+            return Ok(None);
+        }
+        let record = self.pending.split_off(Self::RECORD_HEADER_LEN);
+        self.pending.clear();
+        Ok(Some(record))
     }
 }
 
-/// Reads a log from buffer and prints it to stdout
-pub fn print_log(log: &Log) {
-    println!(
-        "{timestamp:<12.6} {level:<11}: {msg}",
-        timestamp = log.timestamp,
-        level = <LogLevel as Into<&'static str>>::into(log.level).color(color_for_level(log.level)),
-        msg = log.message
-    );
-    println!(
-        "{}{}{}{}",
-        "└── File: ".dimmed(),
-        log.file_name.dimmed(),
-        ", Line number: ".dimmed(),
-        log.line_number.to_string().dimmed(),
-    );
+impl<'a, R: Read> Iterator for PersistDecoder<'a, R> {
+    type Item = Result<Log, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.read_record() {
+            Ok(Some(record)) => Some(self.decoder.decode(&record)),
+            Ok(None) => {
+                self.finished = true;
+                if self.pending.is_empty() {
+                    None
+                } else {
+                    Some(Err(Error::TruncatedRecord(
+                        std::mem::take(&mut self.pending).len(),
+                    )))
+                }
+            }
+            Err(error) => {
+                self.finished = true;
+                Some(Err(error))
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
 
     fn create_elf_metadata() -> ElfMetadata {
         ElfMetadata {
+            postform_version: POSTFORM_VERSION.to_owned(),
             timestamp_freq: 1_000f64,
-            strings: b"test/my_file.cpp@1234@This is my log message\0test/my_file2.cpp@12343@This is my second log message\0".into_iter().map(|c| c.clone()).collect(),
+            strings: b"test/my_file.cpp@1234@This is my log message\0test/my_file2.cpp@12343@This is my second log message\0".to_vec(),
             log_sections: vec![],
         }
     }
 
+    /// Builds a minimal 32-bit little endian ELF image containing the Postform sections and the
+    /// given absolute symbols.
+    fn create_elf_image(
+        version: &str,
+        strings: &[u8],
+        timestamp_freq: u32,
+        symbols: &[(&str, u32)],
+    ) -> Vec<u8> {
+        fn push_u16(buf: &mut Vec<u8>, value: u16) {
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+        fn push_u32(buf: &mut Vec<u8>, value: u32) {
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let mut strtab = vec![0u8];
+        let mut symtab = vec![0u8; 16];
+        for (name, value) in symbols {
+            push_u32(&mut symtab, strtab.len() as u32);
+            push_u32(&mut symtab, *value);
+            push_u32(&mut symtab, 0);
+            // STB_GLOBAL, STT_NOTYPE, SHN_ABS
+            symtab.extend_from_slice(&[0x10, 0]);
+            push_u16(&mut symtab, 0xfff1);
+            strtab.extend_from_slice(name.as_bytes());
+            strtab.push(0);
+        }
+
+        let mut version = version.as_bytes().to_vec();
+        version.push(0);
+        let sections: [(&str, u32, &[u8]); 6] = [
+            (".postform_version", 1, &version),
+            (".interned_strings", 1, strings),
+            (".postform_config", 1, &timestamp_freq.to_le_bytes()),
+            (".symtab", 2, &symtab),
+            (".strtab", 3, &strtab),
+            (".shstrtab", 3, &[]),
+        ];
+        let mut shstrtab = vec![0u8];
+        let mut name_offsets = vec![];
+        for (name, ..) in &sections {
+            name_offsets.push(shstrtab.len() as u32);
+            shstrtab.extend_from_slice(name.as_bytes());
+            shstrtab.push(0);
+        }
+
+        let mut image = vec![0x7f, b'E', b'L', b'F', 1, 1, 1];
+        image.resize(16, 0);
+        push_u16(&mut image, 2); // ET_EXEC
+        push_u16(&mut image, 40); // EM_ARM
+        push_u32(&mut image, 1);
+        push_u32(&mut image, 0);
+        push_u32(&mut image, 0);
+        let shoff_position = image.len();
+        push_u32(&mut image, 0);
+        push_u32(&mut image, 0);
+        push_u16(&mut image, 52);
+        push_u16(&mut image, 0);
+        push_u16(&mut image, 0);
+        push_u16(&mut image, 40);
+        push_u16(&mut image, sections.len() as u16 + 1);
+        push_u16(&mut image, sections.len() as u16);
+
+        let mut headers = vec![0u8; 40];
+        for (index, (_, sh_type, data)) in sections.iter().enumerate() {
+            let data = if index == sections.len() - 1 {
+                &shstrtab[..]
+            } else {
+                data
+            };
+            // The symbol table links to the string table and only holds global symbols.
+            let (link, info, entsize) = if *sh_type == 2 { (5, 1, 16) } else { (0, 0, 0) };
+            push_u32(&mut headers, name_offsets[index]);
+            push_u32(&mut headers, *sh_type);
+            push_u32(&mut headers, 0);
+            push_u32(&mut headers, 0);
+            push_u32(&mut headers, image.len() as u32);
+            push_u32(&mut headers, data.len() as u32);
+            push_u32(&mut headers, link);
+            push_u32(&mut headers, info);
+            push_u32(&mut headers, 1);
+            push_u32(&mut headers, entsize);
+            image.extend_from_slice(data);
+        }
+        let shoff = image.len() as u32;
+        image[shoff_position..shoff_position + 4].copy_from_slice(&shoff.to_le_bytes());
+        image.extend_from_slice(&headers);
+        image
+    }
+
+    #[test]
+    fn test_elf_metadata_from_elf_bytes() {
+        let strings = b"main.cpp@10@Debug message\0main.cpp@20@Error message\0";
+        let image = create_elf_image(
+            POSTFORM_VERSION,
+            strings,
+            32_000,
+            &[
+                ("__InternedDebugStart", 0),
+                ("__InternedDebugEnd", 26),
+                ("__InternedErrorStart", 26),
+                ("__InternedErrorEnd", strings.len() as u32),
+            ],
+        );
+
+        let elf_metadata = ElfMetadata::from_elf_bytes(&image, false).unwrap();
+        assert_eq!(elf_metadata.timestamp_freq, 32_000f64);
+        assert_eq!(elf_metadata.strings, strings);
+        assert!(matches!(
+            elf_metadata.get_log_section(0).level,
+            LogLevel::Debug
+        ));
+        assert!(matches!(
+            elf_metadata.get_log_section(26).level,
+            LogLevel::Error
+        ));
+
+        let elf_metadata = ElfMetadata::from_reader(&image[..], false).unwrap();
+        let mut decoder = Decoder::new(&elf_metadata);
+        let log = decoder.decode(&[0x05, 26]).unwrap();
+        assert_eq!(log.message, "Error message");
+        assert_eq!(log.line_number, 20);
+    }
+
+    #[test]
+    fn test_elf_metadata_version_check() {
+        let image = create_elf_image("0.0.0", b"\0", 1, &[]);
+        assert!(matches!(
+            ElfMetadata::from_elf_bytes(&image, false),
+            Err(Error::MismatchedPostformVersions(..))
+        ));
+        assert!(ElfMetadata::from_elf_bytes(&image, true).is_ok());
+        assert!(matches!(
+            ElfMetadata::from_elf_bytes(b"not an elf file", false),
+            Err(Error::ElfParseError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_dictionary_round_trip() {
+        let strings = b"main.cpp@10@Debug message\0main.cpp@20@Error message\0";
+        let image = create_elf_image(
+            POSTFORM_VERSION,
+            strings,
+            32_000,
+            &[
+                ("__InternedDebugStart", 0),
+                ("__InternedDebugEnd", 26),
+                ("__InternedErrorStart", 26),
+                ("__InternedErrorEnd", strings.len() as u32),
+            ],
+        );
+        let elf_metadata = ElfMetadata::from_elf_bytes(&image, false).unwrap();
+
+        let mut dictionary = vec![];
+        elf_metadata.write_dictionary(&mut dictionary).unwrap();
+        assert!(dictionary.len() < image.len());
+        assert!(ElfMetadata::is_dictionary(&dictionary));
+        assert!(!ElfMetadata::is_dictionary(&image));
+
+        let loaded = ElfMetadata::from_bytes(&dictionary, false).unwrap();
+        assert_eq!(loaded.postform_version, POSTFORM_VERSION);
+        assert_eq!(loaded.timestamp_freq, 32_000f64);
+        assert_eq!(loaded.strings, strings);
+        let mut decoder = Decoder::new(&loaded);
+        let log = decoder.decode(&[0x05, 26]).unwrap();
+        assert!(matches!(log.level, LogLevel::Error));
+        assert_eq!(log.message, "Error message");
+
+        assert!(matches!(
+            ElfMetadata::from_dictionary(&dictionary[..dictionary.len() - 1], false),
+            Err(Error::IoError { .. })
+        ));
+        dictionary[6] += 1;
+        assert!(matches!(
+            ElfMetadata::from_dictionary(&dictionary[..], false),
+            Err(Error::InvalidDictionary)
+        ));
+    }
+
     #[test]
     fn test_recover_interned_string() {
         let elf_metadata = create_elf_metadata();
         let format_string = elf_metadata.recover_interned_string(45usize).unwrap();
-        let (file_name, line, msg) = Decoder::new(&elf_metadata)
-            .decode_format_string(format_string)
-            .unwrap();
+        let (file_name, line, msg) = decode_format_string(&format_string).unwrap();
         assert_eq!(file_name, "test/my_file2.cpp");
         assert_eq!(line, 12343u32);
         assert_eq!(msg, "This is my second log message");
@@ -534,4 +1220,590 @@ mod tests {
         let log = decoder.format_string(format, args).unwrap();
         assert_eq!(log, "This is the log message And another string goes here");
     }
+
+    extern "C" {
+        fn snprintf(
+            buffer: *mut std::ffi::c_char,
+            size: usize,
+            format: *const std::ffi::c_char,
+            ...
+        ) -> std::ffi::c_int;
+    }
+
+    /// Formats a single argument with the C library implementation of printf.
+    fn c_format<T>(format: &str, arg: T) -> String {
+        let format = std::ffi::CString::new(format).unwrap();
+        let mut buffer = [0u8; 1024];
+        let len = unsafe {
+            snprintf(
+                buffer.as_mut_ptr() as *mut std::ffi::c_char,
+                buffer.len(),
+                format.as_ptr(),
+                arg,
+            )
+        };
+        String::from_utf8(buffer[..len as usize].to_vec()).unwrap()
+    }
+
+    /// Formats a single argument with the Postform decoder.
+    fn postform_format(format: &str, args: &[u8]) -> String {
+        let elf_metadata = create_elf_metadata();
+        let decoder = Decoder::new(&elf_metadata);
+        decoder.format_string(format, args).unwrap()
+    }
+
+    const SIGNED_SPECS: [&str; 13] = [
+        "%d", "%i", "%5d", "%-5d|", "%05d", "%+d", "% d", "%.3d", "%08.3d", "%.0d", "%+-6i|",
+        "%hhd", "%hd",
+    ];
+    const UNSIGNED_SPECS: [&str; 17] = [
+        "%u", "%o", "%x", "%X", "%08x", "%#x", "%#X", "%#o", "%#08x", "%-8x|", "%.5u", "%#.0o",
+        "%.0x", "%#10.4x", "%hhu", "%hx", "%3hho",
+    ];
+
+    #[test]
+    fn test_format_string_signed_matches_printf() {
+        let values = [0i64, 1, -1, 42, -42, 1234567, -1234567, 300, -70000];
+        for spec in SIGNED_SPECS {
+            for value in values {
+                let mut args = vec![];
+                leb128::write::signed(&mut args, value).unwrap();
+                let expected = c_format(spec, value as std::ffi::c_int);
+                assert_eq!(postform_format(spec, &args), expected, "{spec} {value}");
+            }
+        }
+
+        for spec in ["%ld", "%lld", "%+20lli", "%jd", "%zd", "%td"] {
+            for value in [0i64, -1, i64::MAX, i64::MIN] {
+                let mut args = vec![];
+                leb128::write::signed(&mut args, value).unwrap();
+                let expected = c_format(spec, value as std::ffi::c_longlong);
+                assert_eq!(postform_format(spec, &args), expected, "{spec} {value}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_string_unsigned_matches_printf() {
+        let values = [0u64, 1, 8, 255, 300, 0xdeadbeef, 70000];
+        for spec in UNSIGNED_SPECS {
+            for value in values {
+                let mut args = vec![];
+                leb128::write::unsigned(&mut args, value).unwrap();
+                let expected = c_format(spec, value as std::ffi::c_uint);
+                assert_eq!(postform_format(spec, &args), expected, "{spec} {value}");
+            }
+        }
+
+        for spec in ["%lu", "%llx", "%#llo", "%020lX", "%zu", "%jx", "%tu"] {
+            for value in [0u64, 1, u64::MAX] {
+                let mut args = vec![];
+                leb128::write::unsigned(&mut args, value).unwrap();
+                let expected = c_format(spec, value as std::ffi::c_ulonglong);
+                assert_eq!(postform_format(spec, &args), expected, "{spec} {value}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_string_strings_and_chars_match_printf() {
+        for spec in ["%s", "%10s", "%-10s|", "%.3s", "%8.2s", "%.0s", "%05s"] {
+            for value in ["", "abc", "a longer string"] {
+                let mut args = value.as_bytes().to_vec();
+                args.push(0);
+                let c_value = std::ffi::CString::new(value).unwrap();
+                let expected = c_format(spec, c_value.as_ptr());
+                assert_eq!(postform_format(spec, &args), expected, "{spec} {value}");
+            }
+        }
+
+        for spec in ["%c", "%3c", "%-3c|"] {
+            let mut args = vec![];
+            leb128::write::unsigned(&mut args, b'@' as u64).unwrap();
+            let expected = c_format(spec, b'@' as std::ffi::c_int);
+            assert_eq!(postform_format(spec, &args), expected, "{spec}");
+        }
+    }
+
+    #[test]
+    fn test_format_string_pointers_match_printf() {
+        for spec in ["%p", "%20p", "%-20p|", "%+p", "%010p", "%.8p"] {
+            for value in [0usize, 0x10, 0x12341234] {
+                let mut args = vec![];
+                leb128::write::unsigned(&mut args, value as u64).unwrap();
+                let expected = c_format(spec, value as *const std::ffi::c_void);
+                assert_eq!(postform_format(spec, &args), expected, "{spec} {value}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_string_percent_sign() {
+        assert_eq!(postform_format("100%% and %5%", &[]), "100% and %");
+    }
+
+    #[test]
+    fn test_format_string_invalid_conversion() {
+        let elf_metadata = create_elf_metadata();
+        let decoder = Decoder::new(&elf_metadata);
+        assert!(matches!(
+            decoder.format_string("%08w", &[0]),
+            Err(Error::InvalidFormatSpecifier('w'))
+        ));
+        assert!(matches!(
+            decoder.format_string("trailing %-8", &[]),
+            Err(Error::InvalidFormatSpecifier('%'))
+        ));
+    }
+
+    #[test]
+    fn test_format_string_floats_match_printf() {
+        let specs = [
+            "%f", "%F", "%.0f", "%#.0f", "%.3f", "%10.2f", "%-10.2f|", "%010.2f", "%+f", "% f",
+            "%e", "%E", "%.0e", "%#.0e", "%.3e", "%+12.4e", "%012.3e", "%g", "%G", "%.0g", "%.3g",
+            "%#g", "%#.3g", "%10g", "%-10g|", "%012g", "%.10g", "%a", "%A", "%.0a", "%.1a", "%.3a",
+            "%.15a", "%#a", "%20a", "%020a", "%+a", "%lf", "%Lf",
+        ];
+        let values = [
+            0.0f64,
+            -0.0,
+            0.5,
+            1.5,
+            2.5,
+            -1.0,
+            1.0 / 3.0,
+            100.0,
+            123456.789,
+            -0.000012345,
+            1e-5,
+            1e300,
+            5e-324,
+            2.2250738585072014e-308,
+            1.9999999999999998,
+            f64::MAX,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NAN,
+        ];
+        for spec in specs {
+            for value in values {
+                let args = value.to_le_bytes();
+                // The decoder always receives a double, so compare against the double format
+                let c_spec = spec.replace('L', "");
+                let expected = c_format(&c_spec, value);
+                assert_eq!(postform_format(spec, &args), expected, "{spec} {value}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_string_float_missing_argument() {
+        let elf_metadata = create_elf_metadata();
+        let decoder = Decoder::new(&elf_metadata);
+        assert!(matches!(
+            decoder.format_string("%f", &[0, 0, 0]),
+            Err(Error::InvalidLogMessage)
+        ));
+    }
+
+    #[test]
+    fn test_decode_reuses_parsed_log_site() {
+        let elf_metadata = ElfMetadata {
+            postform_version: POSTFORM_VERSION.to_owned(),
+            timestamp_freq: 1_000f64,
+            strings: b"file.cpp@10@Value: %d, mail: a@b.c\0".to_vec(),
+            log_sections: vec![],
+        };
+        let mut decoder = Decoder::new(&elf_metadata);
+        for (timestamp, value) in [(1000u64, 5i64), (2000, -7)] {
+            let mut message = vec![];
+            leb128::write::unsigned(&mut message, timestamp).unwrap();
+            leb128::write::unsigned(&mut message, 0).unwrap();
+            leb128::write::signed(&mut message, value).unwrap();
+
+            let log = decoder.decode(&message).unwrap();
+            assert_eq!(log.timestamp, timestamp as f64 / 1000.0);
+            assert_eq!(&*log.file_name, "file.cpp");
+            assert_eq!(log.line_number, 10);
+            assert_eq!(log.message, format!("Value: {}, mail: a@b.c", value));
+        }
+        assert_eq!(decoder.log_sites.len(), 1);
+    }
+
+    #[test]
+    fn test_tokenize_format_string_merges_literals() {
+        let tokens = tokenize_format_string("100%% done: %5.1f%%").unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert!(matches!(&tokens[0], FormatToken::Literal(text) if text == "100% done: "));
+        assert!(matches!(&tokens[1], FormatToken::Conversion(spec, _) if spec.conversion == 'f'));
+        assert!(matches!(&tokens[2], FormatToken::Literal(text) if text == "%"));
+    }
+
+    #[test]
+    fn test_decode_malformed_messages() {
+        let elf_metadata = ElfMetadata {
+            postform_version: POSTFORM_VERSION.to_owned(),
+            timestamp_freq: 1f64,
+            strings: b"f.cpp@1@%s\0f.cpp@2@%k\0f.cpp@3@%c\0".to_vec(),
+            log_sections: vec![],
+        };
+        let mut decoder = Decoder::new(&elf_metadata);
+
+        // Empty message and truncated LEB128 timestamp
+        assert!(matches!(decoder.decode(&[]), Err(Error::InvalidLogMessage)));
+        assert!(matches!(
+            decoder.decode(&[0x80]),
+            Err(Error::InvalidLogMessage)
+        ));
+        // Interned string pointer past the end of the interned strings
+        assert!(matches!(
+            decoder.decode(&[0, 0x7f]),
+            Err(Error::InvalidInternedStringPointer(0x7f))
+        ));
+        // String argument without a NUL terminator
+        assert!(matches!(
+            decoder.decode(&[0, 0, b'a', b'b']),
+            Err(Error::MissingLogArgument)
+        ));
+        // Interned string argument out of bounds
+        assert!(matches!(
+            decoder.decode(&[0, 11, 0x80, 0x01]),
+            Err(Error::InvalidInternedStringPointer(0x80))
+        ));
+        // Invalid character code point
+        assert!(matches!(
+            decoder.decode(&[0, 22, 0x80, 0xb0, 0x03]),
+            Err(Error::InvalidLogMessage)
+        ));
+    }
+
+    #[test]
+    fn test_serial_decoder_reports_frames() {
+        let elf_metadata = create_elf_metadata();
+        let mut decoder = SerialDecoder::new(&elf_metadata);
+
+        let mut data = rcobs::encode(&[0x01, 0x00]);
+        data.push(0);
+        // Valid rcobs frame with an out of bounds interned string pointer
+        let invalid_log = rcobs::encode(&[0x01, 0x7f]);
+        data.extend_from_slice(&invalid_log);
+        data.push(0);
+        // Invalid rcobs frame
+        data.extend_from_slice(&[0x05, 0x00]);
+
+        let mut results = vec![];
+        for chunk in data.chunks(3) {
+            decoder.feed_and_do(chunk, |result| results.push(result));
+        }
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[0].as_ref().unwrap().message,
+            "This is my log message"
+        );
+        assert!(matches!(
+            &results[1],
+            Err(FrameError::Decode {
+                frame,
+                error: Error::InvalidInternedStringPointer(0x7f)
+            }) if *frame == invalid_log
+        ));
+        assert!(matches!(
+            &results[2],
+            Err(error @ FrameError::Rcobs { .. }) if error.frame() == [0x05]
+        ));
+    }
+
+    #[test]
+    fn test_serial_decoder_recovers_from_bad_frames() {
+        let elf_metadata = create_elf_metadata();
+        let mut decoder = SerialDecoder::with_max_frame_len(&elf_metadata, 8);
+        let valid_log = rcobs::encode(&[0x01, 0x00]);
+
+        // Tail of a frame sent before the decoder was connected
+        let mut data = vec![0x7f, 0x13, 0x00];
+        data.extend_from_slice(&valid_log);
+        data.push(0);
+        // Oversized frame, dropped until the next delimiter
+        data.extend_from_slice(&[0x55; 20]);
+        data.push(0);
+        data.extend_from_slice(&valid_log);
+        data.push(0);
+        // Frame that can't be decoded
+        data.extend_from_slice(&[0x05, 0x00]);
+
+        let mut results = vec![];
+        decoder.feed_and_do(&data, |result| results.push(result));
+
+        assert_eq!(results.len(), 4);
+        assert!(results[0].is_ok());
+        assert!(matches!(
+            &results[1],
+            Err(error @ FrameError::Oversized { len: 20 }) if error.frame().is_empty()
+        ));
+        assert!(results[2].is_ok());
+        assert!(matches!(&results[3], Err(FrameError::Rcobs { .. })));
+        assert_eq!(
+            decoder.stats(),
+            SerialDecoderStats {
+                frames_ok: 2,
+                frames_failed: 2,
+                bytes_discarded: 2 + 20 + 1,
+            }
+        );
+        assert!(decoder.rcobs_msg_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_serial_decoder_explicit_resync() {
+        let elf_metadata = create_elf_metadata();
+        let mut decoder = SerialDecoder::new(&elf_metadata);
+        let valid_log = rcobs::encode(&[0x01, 0x00]);
+
+        let mut data = vec![0x00];
+        data.extend_from_slice(&valid_log);
+        data.push(0);
+        let mut results = vec![];
+        decoder.feed_and_do(&data, |result| results.push(result));
+        decoder.feed_and_do(&valid_log[..1], |result| results.push(result));
+
+        // The stream restarts in the middle of a frame that can't be decoded
+        decoder.resync();
+        let mut data = vec![0x7f, 0x13, 0x00];
+        data.extend_from_slice(&valid_log);
+        data.push(0);
+        decoder.feed_and_do(&data, |result| results.push(result));
+
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(
+            decoder.stats(),
+            SerialDecoderStats {
+                frames_ok: 2,
+                frames_failed: 0,
+                bytes_discarded: 1 + 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_serial_decoder_capture_replay() {
+        let elf_metadata = create_elf_metadata();
+        let mut stream = vec![0];
+        for message in [&[0x01u8, 0x00][..], &[0x02, 45]] {
+            stream.extend_from_slice(&rcobs::encode(message));
+            stream.push(0);
+        }
+
+        let header = CaptureHeader::new(&elf_metadata, b"", "serial", None, true).unwrap();
+        let mut writer = CaptureWriter::new(vec![], &header).unwrap();
+        for chunk in stream.chunks(3) {
+            writer
+                .write_chunk(std::time::SystemTime::now(), chunk)
+                .unwrap();
+        }
+        let capture = writer.into_inner();
+
+        // The capture is self-contained
+        let elf_metadata = ElfMetadata::from_bytes(&capture, false).unwrap();
+        let mut decoder = SerialDecoder::new(&elf_metadata);
+        let mut messages = vec![];
+        for chunk in CaptureReader::new(&capture[..]).unwrap() {
+            decoder.feed_and_do(&chunk.unwrap().data, |log| {
+                messages.push(log.unwrap().message);
+            });
+        }
+        assert_eq!(
+            messages,
+            ["This is my log message", "This is my second log message"]
+        );
+    }
+
+    #[test]
+    fn test_serial_decoder_host_timestamp() {
+        let elf_metadata = create_elf_metadata();
+        let mut decoder = SerialDecoder::new(&elf_metadata);
+        let mut data = vec![0];
+        data.extend_from_slice(&rcobs::encode(&[0x01, 0x00]));
+        let received = UNIX_EPOCH + Duration::from_secs(1_650_000_000);
+
+        let mut logs = vec![];
+        decoder.feed_and_do_at(&data, UNIX_EPOCH, |log| logs.push(log.unwrap()));
+        decoder.feed_and_do_at(&[0], received, |log| logs.push(log.unwrap()));
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].host_timestamp, Some(received));
+        assert_eq!(logs[0].message, "This is my log message");
+    }
+
+    #[test]
+    fn test_serial_decoder_channel() {
+        let elf_metadata = create_elf_metadata();
+        let mut data = vec![0];
+        data.extend_from_slice(&rcobs::encode(&[0x01, 0x00]));
+        data.push(0);
+
+        let mut logs = vec![];
+        let mut decoder = SerialDecoder::new(&elf_metadata);
+        decoder.feed_and_do(&data, |log| logs.push(log.unwrap()));
+        decoder.set_channel(Some("Radio".to_owned()));
+        decoder.set_core(Some(1));
+        decoder.feed_and_do(&data[1..], |log| logs.push(log.unwrap()));
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].channel, None);
+        assert_eq!(logs[0].core, None);
+        assert_eq!(logs[1].channel.as_deref(), Some("Radio"));
+        assert_eq!(logs[1].core, Some(1));
+        assert_eq!(logs[1].message, "This is my log message");
+    }
+
+    #[test]
+    fn test_persist_decoder() {
+        let elf_metadata = create_elf_metadata();
+        let mut data = vec![];
+        for record in [&[0x01u8, 0x00][..], &[0x02, 0x7f], &[0x03, 45]] {
+            data.extend_from_slice(&(record.len() as u32).to_le_bytes());
+            data.extend_from_slice(record);
+        }
+        // Record cut short by the end of the stream
+        data.extend_from_slice(&[0x08, 0x00, 0x00, 0x00, 0x04]);
+
+        let logs: Vec<_> = PersistDecoder::new(&elf_metadata, &data[..]).collect();
+        assert_eq!(logs.len(), 4);
+        assert_eq!(logs[0].as_ref().unwrap().message, "This is my log message");
+        assert!(matches!(
+            logs[1],
+            Err(Error::InvalidInternedStringPointer(0x7f))
+        ));
+        assert_eq!(
+            logs[2].as_ref().unwrap().message,
+            "This is my second log message"
+        );
+        assert!(matches!(logs[3], Err(Error::TruncatedRecord(5))));
+
+        assert!(PersistDecoder::new(&elf_metadata, &[][..]).next().is_none());
+        assert!(matches!(
+            PersistDecoder::new(&elf_metadata, &[0x01, 0x00][..]).collect::<Vec<_>>()[..],
+            [Err(Error::TruncatedRecord(2))]
+        ));
+    }
+
+    #[test]
+    fn test_persist_decoder_growing_stream() {
+        let elf_metadata = create_elf_metadata();
+        let mut decoder = PersistDecoder::new(&elf_metadata, std::io::Cursor::new(vec![]));
+        assert!(decoder.next_available().is_none());
+
+        // The record is written in several steps
+        let record = [0x02, 0x00, 0x00, 0x00, 0x01, 0x00];
+        for (index, byte) in record.iter().enumerate() {
+            decoder.get_mut().get_mut().push(*byte);
+            let log = decoder.next_available();
+            if index == record.len() - 1 {
+                assert_eq!(log.unwrap().unwrap().message, "This is my log message");
+            } else {
+                assert!(log.is_none());
+            }
+        }
+        assert!(decoder.next_available().is_none());
+
+        // Partial record followed by a truncation of the stream
+        decoder.get_mut().get_mut().extend_from_slice(&record[..3]);
+        assert!(decoder.next_available().is_none());
+        decoder.reset(std::io::Cursor::new(
+            [0x02, 0x00, 0x00, 0x00, 0x01, 45].to_vec(),
+        ));
+        assert_eq!(
+            decoder.next_available().unwrap().unwrap().message,
+            "This is my second log message"
+        );
+        assert!(decoder.next().is_none());
+    }
+
+    #[test]
+    fn test_decode_structured_arguments() {
+        let elf_metadata = ElfMetadata {
+            postform_version: POSTFORM_VERSION.to_owned(),
+            timestamp_freq: 1f64,
+            strings: b"f.cpp@1@%d %hhu %.1f %c %s %p %k\0interned\0".to_vec(),
+            log_sections: vec![],
+        };
+        let mut message = vec![];
+        leb128::write::unsigned(&mut message, 0).unwrap();
+        leb128::write::unsigned(&mut message, 0).unwrap();
+        leb128::write::signed(&mut message, -3).unwrap();
+        leb128::write::unsigned(&mut message, 0x1ff).unwrap();
+        message.extend_from_slice(&2.25f64.to_le_bytes());
+        leb128::write::unsigned(&mut message, 'x' as u64).unwrap();
+        message.extend_from_slice(b"text\0");
+        leb128::write::unsigned(&mut message, 0x2000).unwrap();
+        leb128::write::unsigned(&mut message, 33).unwrap();
+
+        let log = Decoder::new(&elf_metadata).decode(&message).unwrap();
+        assert_eq!(log.message, "-3 255 2.2 x text 0x2000 interned");
+        assert_eq!(&*log.format, "%d %hhu %.1f %c %s %p %k");
+        assert_eq!(
+            log.args,
+            vec![
+                ArgValue::Signed(-3),
+                ArgValue::Unsigned(0xff),
+                ArgValue::Float(2.25),
+                ArgValue::Char('x'),
+                ArgValue::String("text".to_owned()),
+                ArgValue::Pointer(0x2000),
+                ArgValue::InternedString("interned".to_owned()),
+            ]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_log_json_round_trip() {
+        let mut log = Log {
+            timestamp: 1.5,
+            host_timestamp: None,
+            channel: None,
+            core: None,
+            level: LogLevel::Warning,
+            message: "Temperature: 23.50".to_owned(),
+            file_name: "main.cpp".into(),
+            line_number: 12,
+            format: "Temperature: %.2f".into(),
+            args: vec![ArgValue::Float(23.5)],
+        };
+        let json = serde_json::to_string(&log).unwrap();
+        assert_eq!(
+            json,
+            r#"{"timestamp":1.5,"level":"Warning","message":"Temperature: 23.50","file_name":"main.cpp","line_number":12,"format":"Temperature: %.2f","args":[{"Float":23.5}]}"#
+        );
+
+        let decoded: Log = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.message, log.message);
+        assert_eq!(decoded.args, log.args);
+        assert_eq!(decoded.host_timestamp, None);
+
+        log.host_timestamp = Some(UNIX_EPOCH + Duration::from_micros(1_650_000_000_123_456));
+        log.channel = Some("Radio".to_owned());
+        log.core = Some(1);
+        let json = serde_json::to_string(&log).unwrap();
+        assert!(json.starts_with(
+            r#"{"timestamp":1.5,"host_timestamp":"2022-04-15T05:20:00.123456Z","channel":"Radio","core":1,"level""#
+        ));
+        let decoded: Log = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.host_timestamp, log.host_timestamp);
+        assert_eq!(decoded.channel, log.channel);
+        assert_eq!(decoded.core, log.core);
+
+        // Floats that JSON can't represent
+        log.args = vec![
+            ArgValue::Float(f64::NAN),
+            ArgValue::Float(f64::INFINITY),
+            ArgValue::Float(f64::NEG_INFINITY),
+        ];
+        let json = serde_json::to_string(&log).unwrap();
+        assert!(json.ends_with(r#""args":[{"Float":"NaN"},{"Float":"inf"},{"Float":"-inf"}]}"#));
+        let decoded: Log = serde_json::from_str(&json).unwrap();
+        assert!(matches!(decoded.args[0], ArgValue::Float(value) if value.is_nan()));
+        assert_eq!(decoded.args[1..], log.args[1..]);
+        assert!(serde_json::from_str::<ArgValue>(r#"{"Float":"infinity"}"#).is_err());
+    }
 }
@@ -172,7 +172,7 @@ fn run_system_tests(root_dir: &Path, build_dir: &Path) -> Result<()> {
     postform_test.push("postform_test");
 
     cmd!("{postform_test} {file_name}").run()?;
-    let text = cmd!("cargo run --bin=postform_persist -- {postform_test} {file_name}").read()?;
+    let text = cmd!("cargo run --bin=postform -- persist {postform_test} {file_name}").read()?;
 
     let mut blessed_file = root_dir.to_owned();
     blessed_file.push("expected_log.txt");
@@ -228,7 +228,7 @@ fn bless_cxx_tests() {
     postform_test.push("postform_test");
 
     cmd!("{postform_test} {file_name}").run().unwrap();
-    let text = cmd!("cargo run --bin=postform_persist -- {postform_test} {file_name}")
+    let text = cmd!("cargo run --bin=postform -- persist {postform_test} {file_name}")
         .read()
         .unwrap();
 